@@ -0,0 +1,8 @@
+create table counters(
+  channel text not null,
+  name text not null,
+  value integer not null default 0,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp,
+  primary key (channel, name)
+);
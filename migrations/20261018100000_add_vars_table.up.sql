@@ -0,0 +1,9 @@
+create table vars(
+  channel text not null,
+  key text not null,
+  value text not null,
+  created_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp,
+  primary key (channel, key)
+);
@@ -0,0 +1,5 @@
+alter table replies drop column stream_condition;
+alter table replies drop column daily_active_until;
+alter table replies drop column daily_active_from;
+alter table replies drop column active_until;
+alter table replies drop column active_from;
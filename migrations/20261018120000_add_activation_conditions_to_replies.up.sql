@@ -0,0 +1,5 @@
+alter table replies add column active_from timestamptz;
+alter table replies add column active_until timestamptz;
+alter table replies add column daily_active_from time;
+alter table replies add column daily_active_until time;
+alter table replies add column stream_condition text;
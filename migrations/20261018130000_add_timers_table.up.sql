@@ -0,0 +1,12 @@
+create table timers(
+  id integer not null primary key,
+  channel text not null,
+  templates json not null,
+  interval_secs integer not null,
+  min_chat_messages integer not null default 0,
+  live_only boolean not null default 1,
+  enabled boolean not null default false,
+  created_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
@@ -0,0 +1,13 @@
+create table auto_gambas(
+  channel text not null primary key,
+  region text not null,
+  summoner_name text not null,
+  title text not null default 'Will we win?',
+  win_outcome text not null default 'Yes',
+  lose_outcome text not null default 'No',
+  lock_after_secs integer not null default 180,
+  enabled boolean not null default false,
+  created_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
@@ -0,0 +1,17 @@
+create table gambas(
+  id text not null primary key,
+  channel text not null,
+  title text not null,
+  sides json not null,
+  state text not null,
+  winner text,
+  winner_idx integer,
+  total_users integer not null,
+  total_betted_channel_points integer not null,
+  started_at timestamptz not null,
+  ended_at timestamptz,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
+
+create index gambas_channel_started_at_idx on gambas(channel, started_at);
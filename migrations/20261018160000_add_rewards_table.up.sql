@@ -0,0 +1,12 @@
+create table rewards(
+  id text not null primary key,
+  channel text not null,
+  action text,
+  action_arg text,
+  template text,
+  settle boolean not null default false,
+  enabled boolean not null default false,
+  created_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
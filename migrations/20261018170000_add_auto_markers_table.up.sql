@@ -0,0 +1,9 @@
+create table auto_markers(
+  channel text not null primary key,
+  region text not null,
+  summoner_name text not null,
+  enabled boolean not null default false,
+  created_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
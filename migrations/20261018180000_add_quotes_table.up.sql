@@ -0,0 +1,10 @@
+create table quotes(
+  channel text not null,
+  number integer not null,
+  text text not null,
+  category text,
+  quoted_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp,
+  primary key (channel, number)
+);
@@ -0,0 +1,13 @@
+create table scheduled_messages(
+  id integer not null primary key,
+  channel text not null,
+  kind text not null,
+  text text not null,
+  requested_by text not null,
+  due_at timestamptz not null,
+  sent_at timestamptz,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
+
+create index scheduled_messages_due_at_idx on scheduled_messages(due_at) where sent_at is null;
@@ -0,0 +1,28 @@
+create table raffles(
+  id integer not null primary key,
+  channel text not null,
+  keyword text not null,
+  sub_luck integer not null default 1,
+  restriction text not null default 'Everyone',
+  state text not null default 'Open',
+  opened_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
+
+create table raffle_entries(
+  raffle_id integer not null references raffles(id),
+  user_login text not null,
+  user_name text not null,
+  tickets integer not null,
+  created_at timestamptz not null default current_timestamp,
+  primary key (raffle_id, user_login)
+);
+
+create table raffle_winners(
+  raffle_id integer not null references raffles(id),
+  user_login text not null,
+  user_name text not null,
+  drawn_at timestamptz not null default current_timestamp,
+  primary key (raffle_id, user_login)
+);
@@ -0,0 +1,2 @@
+drop table points;
+drop table points_settings;
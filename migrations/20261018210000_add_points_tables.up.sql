@@ -0,0 +1,26 @@
+create table points_settings(
+  channel text not null primary key,
+  per_message integer not null default 1,
+  message_cooldown_secs integer not null default 30,
+  per_active_minute integer not null default 1,
+  active_window_secs integer not null default 600,
+  roulette_win_pct integer not null default 45,
+  duel_timeout_secs integer not null default 60,
+  max_bet integer not null default 0,
+  enabled boolean not null default false,
+  created_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
+
+create table points(
+  channel text not null,
+  user_login text not null,
+  user_name text not null,
+  balance integer not null default 0,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp,
+  primary key (channel, user_login)
+);
+
+create index points_balance_idx on points(channel, balance desc);
@@ -0,0 +1,2 @@
+drop table trivia_scores;
+drop table trivia_settings;
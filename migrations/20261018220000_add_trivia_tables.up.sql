@@ -0,0 +1,22 @@
+create table trivia_settings(
+  channel text not null primary key,
+  rounds integer not null default 5,
+  round_secs integer not null default 60,
+  hint_secs integer not null default 15,
+  points integer not null default 50,
+  created_by text not null,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp
+);
+
+create table trivia_scores(
+  channel text not null,
+  user_login text not null,
+  user_name text not null,
+  wins integer not null default 0,
+  created_at timestamptz not null default current_timestamp,
+  updated_at timestamptz not null default current_timestamp,
+  primary key (channel, user_login)
+);
+
+create index trivia_scores_wins_idx on trivia_scores(channel, wins desc);
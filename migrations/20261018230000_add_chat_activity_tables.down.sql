@@ -0,0 +1,3 @@
+drop table emote_usages;
+drop table stream_chatters;
+drop table user_activities;
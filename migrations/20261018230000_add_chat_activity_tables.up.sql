@@ -0,0 +1,32 @@
+create table user_activities(
+  channel text not null,
+  user_login text not null,
+  user_name text not null,
+  first_seen_at timestamptz not null,
+  last_seen_at timestamptz not null,
+  messages_count integer not null default 0,
+  active_days integer not null default 0,
+  last_active_day date not null,
+  primary key (channel, user_login)
+);
+
+create index user_activities_messages_count_idx on user_activities(channel, messages_count desc);
+
+create table stream_chatters(
+  channel text not null,
+  stream_started_at timestamptz not null,
+  user_login text not null,
+  messages_count integer not null default 0,
+  primary key (channel, stream_started_at, user_login)
+);
+
+create table emote_usages(
+  channel text not null,
+  emote_id text not null,
+  emote_code text not null,
+  uses integer not null default 0,
+  last_used_at timestamptz not null,
+  primary key (channel, emote_id)
+);
+
+create index emote_usages_uses_idx on emote_usages(channel, uses desc);
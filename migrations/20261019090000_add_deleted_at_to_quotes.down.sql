@@ -0,0 +1 @@
+alter table quotes drop column deleted_at;
@@ -0,0 +1 @@
+alter table quotes add column deleted_at timestamptz;
@@ -101,6 +101,43 @@ impl Champion {
         }))
     }
 
+    pub async fn random(executor: impl SqliteExecutor<'_>) -> sqlx::Result<Option<Champion>> {
+        Ok(sqlx::query!(
+            r#"
+                select
+                    version,
+                    id,
+                    key as "key!: ChampionKey",
+                    name,
+                    title,
+                    blurb,
+                    info as "info!: Json<Info>",
+                    image as "image!: Json<Image>",
+                    tags as "tags!: Json<Vec<Tag>>",
+                    partype,
+                    stats as "stats!: Json<HashMap<String, f64>>"
+                from champions
+                order by random()
+                limit 1
+            "#
+        )
+        .fetch_optional(executor)
+        .await?
+        .map(|r| Self {
+            version: r.version,
+            id: r.id,
+            key: r.key,
+            name: r.name,
+            title: r.title,
+            blurb: r.blurb,
+            info: r.info.0,
+            image: r.image.0,
+            tags: r.tags.0,
+            partype: r.partype,
+            stats: r.stats.0,
+        }))
+    }
+
     pub async fn truncate(executor: impl SqliteExecutor<'_>) -> sqlx::Result<()> {
         sqlx::query!(r#"delete from champions"#).execute(executor).await?;
         Ok(())
@@ -150,7 +187,7 @@ pub enum Sprite {
     Champion5Png,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Dummy, sqlx::Type)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Dummy, sqlx::Type)]
 #[sqlx(type_name = "TEXT")]
 pub enum Tag {
     Assassin,
@@ -1 +1,8 @@
+pub mod channels;
+pub mod clips;
+pub mod eventsub;
 pub mod helpers;
+pub mod markers;
+pub mod polls;
+pub mod predictions;
+pub mod rewards;
@@ -0,0 +1,89 @@
+use twitch_api::helix::channels::ChannelInformation;
+use twitch_api::helix::channels::Follower;
+use twitch_api::helix::channels::GetChannelFollowersRequest;
+use twitch_api::helix::channels::ModifyChannelInformationBody;
+use twitch_api::helix::channels::ModifyChannelInformationRequest;
+use twitch_api::helix::chat::SendAShoutoutRequest;
+use twitch_api::helix::search::search_categories::Category;
+use twitch_api::helix::search::SearchCategoriesRequest;
+use twitch_api::helix::EmptyBody;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::CategoryId;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+pub const MAX_SEARCHED_CATEGORIES: usize = 10;
+
+/// Categories matching the query, as ranked by Twitch.
+pub async fn search_categories(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    query: &str,
+) -> anyhow::Result<Vec<Category>> {
+    let request = SearchCategoriesRequest::query(query).first(MAX_SEARCHED_CATEGORIES);
+
+    Ok(helix_client.req_get(request, token).await?.data)
+}
+
+/// Updates only the given title and category, leaving the rest of the channel information untouched.
+pub async fn modify_channel_information(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    title: Option<&str>,
+    category_id: Option<&CategoryId>,
+) -> anyhow::Result<()> {
+    let mut body = ModifyChannelInformationBody::new();
+    if let Some(title) = title {
+        body.title(title);
+    }
+    if let Some(category_id) = category_id {
+        body.game_id(category_id);
+    }
+
+    helix_client
+        .req_patch(
+            ModifyChannelInformationRequest::broadcaster_id(broadcaster_id),
+            body,
+            token,
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_channel_information(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+) -> anyhow::Result<Option<ChannelInformation>> {
+    Ok(helix_client.get_channel_from_id(broadcaster_id, token).await?)
+}
+
+/// The follow of the user to the broadcaster channel, if any.
+///
+/// Requires a broadcaster or moderator token with the `moderator:read:followers` scope.
+pub async fn get_follower(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    user_id: &UserId,
+) -> anyhow::Result<Option<Follower>> {
+    let request = GetChannelFollowersRequest::broadcaster_id(broadcaster_id).user_id(user_id);
+
+    Ok(helix_client.req_get(request, token).await?.data.into_iter().next())
+}
+
+/// Sends the official Twitch shoutout, only allowed while live and subject to Twitch cooldowns.
+pub async fn send_shoutout(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    from_broadcaster_id: &UserId,
+    to_broadcaster_id: &UserId,
+) -> anyhow::Result<()> {
+    let request = SendAShoutoutRequest::new(from_broadcaster_id, to_broadcaster_id, &token.user_id);
+
+    helix_client.req_post(request, EmptyBody, token).await?;
+
+    Ok(())
+}
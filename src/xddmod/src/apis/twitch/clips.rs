@@ -0,0 +1,24 @@
+use twitch_api::helix::clips::CreateClipRequest;
+use twitch_api::helix::clips::CreatedClip;
+use twitch_api::helix::EmptyBody;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+pub const CLIPS_URL: &str = "https://clips.twitch.tv";
+
+/// Clips the last seconds of the live stream of the broadcaster.
+pub async fn create_clip(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+) -> anyhow::Result<CreatedClip> {
+    Ok(helix_client
+        .req_post(CreateClipRequest::broadcaster_id(broadcaster_id), EmptyBody, token)
+        .await?
+        .data)
+}
+
+pub fn clip_url(clip: &CreatedClip) -> String {
+    format!("{}/{}", CLIPS_URL, clip.id)
+}
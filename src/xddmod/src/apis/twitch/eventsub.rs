@@ -0,0 +1,605 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use url::Url;
+
+pub mod mock;
+
+pub const EVENTSUB_WS_URL: &str = "wss://eventsub.wss.twitch.tv/ws";
+pub const HELIX_API_URL: &str = "https://api.twitch.tv/helix";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// Extra time allowed over the keepalive timeout advertised by Twitch before considering the connection dead.
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(5);
+
+/// EventSub WebSocket subscriber.
+///
+/// Creates the configured subscriptions on every new session and forwards the typed notifications to the given sender.
+/// Reconnects following `session_reconnect` messages, closed connections and missing keepalives.
+#[derive(Debug, Clone)]
+pub struct EventSubClient {
+    pub ws_url: Url,
+    pub helix_url: Url,
+    pub client_id: String,
+    pub token: UserToken,
+    pub http_client: reqwest::Client,
+    pub subscriptions: Vec<Subscription>,
+}
+
+impl EventSubClient {
+    pub async fn run(&mut self, sender: UnboundedSender<Event>) {
+        let mut ws_url = self.ws_url.clone();
+        let mut is_reconnect = false;
+
+        loop {
+            match self.consume_session(&ws_url, is_reconnect, &sender).await {
+                Ok(Some(reconnect_url)) => {
+                    ws_url = reconnect_url;
+                    is_reconnect = true;
+                }
+                Ok(None) => {
+                    eprintln!("EventSub connection closed, reconnecting");
+                    ws_url = self.ws_url.clone();
+                    is_reconnect = false;
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+                Err(error) => {
+                    eprintln!("EventSub connection error, reconnecting, error: {:?}", error);
+                    ws_url = self.ws_url.clone();
+                    is_reconnect = false;
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+
+            if sender.is_closed() {
+                return;
+            }
+        }
+    }
+
+    /// Returns the URL to reconnect to if Twitch asked for a reconnection.
+    async fn consume_session(
+        &mut self,
+        ws_url: &Url,
+        is_reconnect: bool,
+        sender: &UnboundedSender<Event>,
+    ) -> anyhow::Result<Option<Url>> {
+        let (mut ws_stream, _) = tokio_tungstenite::connect_async(ws_url.as_str()).await?;
+        let mut keepalive_timeout: Option<Duration> = None;
+
+        loop {
+            let next_message = match keepalive_timeout {
+                Some(keepalive_timeout) => tokio::time::timeout(keepalive_timeout + KEEPALIVE_GRACE, ws_stream.next())
+                    .await
+                    .map_err(|_| anyhow!("No EventSub message received within {:?}", keepalive_timeout))?,
+                None => ws_stream.next().await,
+            };
+
+            let text = match next_message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => return Ok(None),
+                Some(Ok(_)) => continue,
+                Some(Err(error)) => return Err(error.into()),
+            };
+
+            let websocket_message = match WebsocketMessage::parse(&text) {
+                Ok(websocket_message) => websocket_message,
+                Err(error) => {
+                    eprintln!("Error parsing EventSub message {:?}, error: {:?}", text, error);
+                    continue;
+                }
+            };
+
+            match websocket_message {
+                WebsocketMessage::SessionWelcome(session) => {
+                    keepalive_timeout = session.keepalive_timeout_seconds.map(Duration::from_secs);
+                    // Subscriptions are carried over to the new session on reconnect.
+                    if !is_reconnect {
+                        self.subscribe_all(&session.id).await;
+                    }
+                }
+                WebsocketMessage::SessionKeepalive => {}
+                WebsocketMessage::SessionReconnect(session) => {
+                    let reconnect_url = session
+                        .reconnect_url
+                        .ok_or_else(|| anyhow!("Missing reconnect_url in session_reconnect message {:?}", text))?;
+                    return Ok(Some(reconnect_url));
+                }
+                WebsocketMessage::Notification(event) => sender.send(event)?,
+                WebsocketMessage::Revocation(subscription) => {
+                    eprintln!("EventSub subscription revoked {:?}", subscription)
+                }
+                WebsocketMessage::Unsupported { message_type } => {
+                    eprintln!("Unsupported EventSub message {:?}, {:?}", message_type, text)
+                }
+            }
+        }
+    }
+
+    async fn subscribe_all(&mut self, session_id: &str) {
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for EventSub subscriptions");
+            if let Err(error) = self.token.refresh_token(&self.http_client).await {
+                eprintln!("Error refreshing token for EventSub subscriptions, error: {:?}", error);
+            }
+        }
+
+        for subscription in &self.subscriptions {
+            if let Err(error) = self.subscribe(subscription, session_id).await {
+                eprintln!(
+                    "Error creating EventSub subscription {:?}, error: {:?}",
+                    subscription, error
+                );
+            }
+        }
+    }
+
+    async fn subscribe(&self, subscription: &Subscription, session_id: &str) -> anyhow::Result<()> {
+        let mut url = self.helix_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid Helix URL {:?}", self.helix_url))?
+            .pop_if_empty()
+            .extend(["eventsub", "subscriptions"]);
+
+        let response = self
+            .http_client
+            .post(url)
+            .header("Client-Id", &self.client_id)
+            .bearer_auth(self.token.access_token.secret())
+            .json(&CreateSubscriptionBody {
+                subscription: subscription.clone(),
+                transport: Transport {
+                    method: "websocket".into(),
+                    session_id: Some(session_id.into()),
+                },
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Unexpected status {:?} creating subscription, response: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Subscription {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: String,
+    pub condition: serde_json::Value,
+}
+
+impl Subscription {
+    pub fn new(kind: &str, version: &str, condition: serde_json::Value) -> Self {
+        Self {
+            kind: kind.into(),
+            version: version.into(),
+            condition,
+        }
+    }
+
+    /// Every subscription handled by [`Event`] for the given broadcaster.
+    pub fn all_for(broadcaster_id: &str, moderator_id: &str) -> Vec<Self> {
+        let broadcaster = json!({ "broadcaster_user_id": broadcaster_id });
+
+        vec![
+            Self::new("channel.prediction.begin", "1", broadcaster.clone()),
+            Self::new("channel.prediction.progress", "1", broadcaster.clone()),
+            Self::new("channel.prediction.lock", "1", broadcaster.clone()),
+            Self::new("channel.prediction.end", "1", broadcaster.clone()),
+            Self::new("channel.poll.begin", "1", broadcaster.clone()),
+            Self::new("channel.poll.progress", "1", broadcaster.clone()),
+            Self::new("channel.poll.end", "1", broadcaster.clone()),
+            Self::new(
+                "channel.channel_points_custom_reward_redemption.add",
+                "1",
+                broadcaster.clone(),
+            ),
+            Self::new("stream.online", "1", broadcaster.clone()),
+            Self::new("stream.offline", "1", broadcaster),
+            Self::new(
+                "channel.follow",
+                "2",
+                json!({ "broadcaster_user_id": broadcaster_id, "moderator_user_id": moderator_id }),
+            ),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CreateSubscriptionBody {
+    #[serde(flatten)]
+    pub subscription: Subscription,
+    pub transport: Transport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Transport {
+    pub method: String,
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub keepalive_timeout_seconds: Option<u64>,
+    pub reconnect_url: Option<Url>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum WebsocketMessage {
+    SessionWelcome(Session),
+    SessionKeepalive,
+    SessionReconnect(Session),
+    Notification(Event),
+    Revocation(serde_json::Value),
+    Unsupported { message_type: String },
+}
+
+impl WebsocketMessage {
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct Raw {
+            metadata: Metadata,
+            payload: Payload,
+        }
+
+        #[derive(Deserialize)]
+        struct Metadata {
+            message_type: String,
+            subscription_type: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Payload {
+            session: Option<Session>,
+            subscription: Option<serde_json::Value>,
+            event: Option<serde_json::Value>,
+        }
+
+        let raw: Raw = serde_json::from_str(text)?;
+        let missing = |field: &str| anyhow!("Missing {} in EventSub {:?} message", field, raw.metadata.message_type);
+
+        Ok(match raw.metadata.message_type.as_str() {
+            "session_welcome" => Self::SessionWelcome(raw.payload.session.ok_or_else(|| missing("session"))?),
+            "session_keepalive" => Self::SessionKeepalive,
+            "session_reconnect" => Self::SessionReconnect(raw.payload.session.ok_or_else(|| missing("session"))?),
+            "notification" => Self::Notification(Event::parse(
+                &raw.metadata
+                    .subscription_type
+                    .ok_or_else(|| missing("subscription_type"))?,
+                raw.payload.event.ok_or_else(|| missing("event"))?,
+            )?),
+            "revocation" => Self::Revocation(raw.payload.subscription.ok_or_else(|| missing("subscription"))?),
+            _ => Self::Unsupported {
+                message_type: raw.metadata.message_type,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Event {
+    PredictionBegin(PredictionEvent),
+    PredictionProgress(PredictionEvent),
+    PredictionLock(PredictionEvent),
+    PredictionEnd(PredictionEvent),
+    PollBegin(PollEvent),
+    PollProgress(PollEvent),
+    PollEnd(PollEvent),
+    RedemptionAdd(RedemptionEvent),
+    StreamOnline(StreamOnlineEvent),
+    StreamOffline(StreamOfflineEvent),
+    Follow(FollowEvent),
+}
+
+impl Event {
+    pub fn parse(subscription_type: &str, event: serde_json::Value) -> anyhow::Result<Self> {
+        Ok(match subscription_type {
+            "channel.prediction.begin" => Self::PredictionBegin(serde_json::from_value(event)?),
+            "channel.prediction.progress" => Self::PredictionProgress(serde_json::from_value(event)?),
+            "channel.prediction.lock" => Self::PredictionLock(serde_json::from_value(event)?),
+            "channel.prediction.end" => Self::PredictionEnd(serde_json::from_value(event)?),
+            "channel.poll.begin" => Self::PollBegin(serde_json::from_value(event)?),
+            "channel.poll.progress" => Self::PollProgress(serde_json::from_value(event)?),
+            "channel.poll.end" => Self::PollEnd(serde_json::from_value(event)?),
+            "channel.channel_points_custom_reward_redemption.add" => {
+                Self::RedemptionAdd(serde_json::from_value(event)?)
+            }
+            "stream.online" => Self::StreamOnline(serde_json::from_value(event)?),
+            "stream.offline" => Self::StreamOffline(serde_json::from_value(event)?),
+            "channel.follow" => Self::Follow(serde_json::from_value(event)?),
+            unsupported => bail!("Unsupported EventSub subscription type {:?}", unsupported),
+        })
+    }
+
+    pub fn broadcaster_user_login(&self) -> &str {
+        match self {
+            Self::PredictionBegin(e)
+            | Self::PredictionProgress(e)
+            | Self::PredictionLock(e)
+            | Self::PredictionEnd(e) => &e.broadcaster_user_login,
+            Self::PollBegin(e) | Self::PollProgress(e) | Self::PollEnd(e) => &e.broadcaster_user_login,
+            Self::RedemptionAdd(e) => &e.broadcaster_user_login,
+            Self::StreamOnline(e) => &e.broadcaster_user_login,
+            Self::StreamOffline(e) => &e.broadcaster_user_login,
+            Self::Follow(e) => &e.broadcaster_user_login,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PredictionEvent {
+    pub id: String,
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub title: String,
+    pub outcomes: Vec<PredictionEventOutcome>,
+    pub started_at: DateTime<Utc>,
+    pub locks_at: Option<DateTime<Utc>>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub winning_outcome_id: Option<String>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PredictionEventOutcome {
+    pub id: String,
+    pub title: String,
+    pub color: String,
+    pub users: Option<i64>,
+    pub channel_points: Option<i64>,
+    pub top_predictors: Option<Vec<TopPredictor>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TopPredictor {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub channel_points_won: Option<i64>,
+    pub channel_points_used: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PollEvent {
+    pub id: String,
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub title: String,
+    pub choices: Vec<PollEventChoice>,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PollEventChoice {
+    pub id: String,
+    pub title: String,
+    pub votes: Option<i64>,
+    pub channel_points_votes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RedemptionEvent {
+    pub id: String,
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub user_input: String,
+    pub status: String,
+    pub reward: RedemptionEventReward,
+    pub redeemed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RedemptionEventReward {
+    pub id: String,
+    pub title: String,
+    pub cost: i64,
+    pub prompt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamOnlineEvent {
+    pub id: String,
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub broadcaster_user_name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamOfflineEvent {
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub broadcaster_user_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FollowEvent {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub followed_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use twitch_api::twitch_oauth2::AccessToken;
+    use twitch_api::twitch_oauth2::ClientId;
+    use twitch_api::types::UserId;
+    use twitch_api::types::UserName;
+
+    use super::mock::MockEventSubServer;
+    use super::*;
+
+    #[test]
+    fn test_websocket_message_parse_works_as_expected() {
+        assert_eq!(
+            WebsocketMessage::SessionWelcome(Session {
+                id: "foo".into(),
+                keepalive_timeout_seconds: Some(10),
+                reconnect_url: None,
+            }),
+            WebsocketMessage::parse(
+                r#"{
+                    "metadata": {"message_id": "1", "message_type": "session_welcome", "message_timestamp": "2023-07-19T14:56:51.634234626Z"},
+                    "payload": {"session": {"id": "foo", "status": "connected", "connected_at": "2023-07-19T14:56:51.616329898Z", "keepalive_timeout_seconds": 10, "reconnect_url": null}}
+                }"#
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            WebsocketMessage::SessionKeepalive,
+            WebsocketMessage::parse(r#"{"metadata": {"message_type": "session_keepalive"}, "payload": {}}"#).unwrap()
+        );
+        assert_eq!(
+            WebsocketMessage::Notification(Event::StreamOffline(StreamOfflineEvent {
+                broadcaster_user_id: "1".into(),
+                broadcaster_user_login: "foo".into(),
+                broadcaster_user_name: "Foo".into(),
+            })),
+            WebsocketMessage::parse(
+                r#"{
+                    "metadata": {"message_type": "notification", "subscription_type": "stream.offline", "subscription_version": "1"},
+                    "payload": {
+                        "subscription": {"type": "stream.offline"},
+                        "event": {"broadcaster_user_id": "1", "broadcaster_user_login": "foo", "broadcaster_user_name": "Foo"}
+                    }
+                }"#
+            )
+            .unwrap()
+        );
+        assert!(WebsocketMessage::parse(
+            r#"{"metadata": {"message_type": "notification", "subscription_type": "foo"}, "payload": {"event": {}}}"#
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_eventsub_client_works_with_mock_server() {
+        let mock = MockEventSubServer::start().await.unwrap();
+        let subscriptions = Subscription::all_for("1", "2");
+        let mut client = test_client(&mock, subscriptions.clone());
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let client_handle = tokio::spawn(async move { client.run(sender).await });
+
+        mock.wait_for_subscriptions(subscriptions.len()).await.unwrap();
+        assert_eq!(subscriptions, mock.subscriptions());
+
+        mock.notify(
+            "stream.online",
+            "1",
+            json!({
+                "id": "42",
+                "broadcaster_user_id": "1",
+                "broadcaster_user_login": "foo",
+                "broadcaster_user_name": "Foo",
+                "type": "live",
+                "started_at": "2020-01-01T00:00:00Z"
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(Event::StreamOnline(StreamOnlineEvent {
+                id: "42".into(),
+                broadcaster_user_id: "1".into(),
+                broadcaster_user_login: "foo".into(),
+                broadcaster_user_name: "Foo".into(),
+                kind: "live".into(),
+                started_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+            })),
+            tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .unwrap()
+        );
+
+        client_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_eventsub_client_skips_unparseable_notifications() {
+        let mock = MockEventSubServer::start().await.unwrap();
+        let subscriptions = Subscription::all_for("1", "2");
+        let mut client = test_client(&mock, subscriptions.clone());
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let client_handle = tokio::spawn(async move { client.run(sender).await });
+
+        mock.wait_for_subscriptions(subscriptions.len()).await.unwrap();
+
+        mock.notify("channel.foo", "1", json!({ "foo": "bar" })).unwrap();
+        mock.notify(
+            "stream.offline",
+            "1",
+            json!({ "broadcaster_user_id": "1", "broadcaster_user_login": "foo", "broadcaster_user_name": "Foo" }),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(Event::StreamOffline(StreamOfflineEvent {
+                broadcaster_user_id: "1".into(),
+                broadcaster_user_login: "foo".into(),
+                broadcaster_user_name: "Foo".into(),
+            })),
+            tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .unwrap()
+        );
+        // The session survived, hence no new subscriptions.
+        assert_eq!(subscriptions, mock.subscriptions());
+
+        client_handle.abort();
+    }
+
+    fn test_client(mock: &MockEventSubServer, subscriptions: Vec<Subscription>) -> EventSubClient {
+        EventSubClient {
+            ws_url: mock.ws_url.clone(),
+            helix_url: mock.helix_url.clone(),
+            client_id: "client_id".into(),
+            token: UserToken::from_existing_unchecked(
+                AccessToken::new("access_token".into()),
+                None,
+                ClientId::new("client_id".into()),
+                None,
+                UserName::new("bar".into()),
+                UserId::new("2".into()),
+                None,
+                Some(Duration::from_secs(3600)),
+            ),
+            http_client: reqwest::Client::new(),
+            subscriptions,
+        }
+    }
+}
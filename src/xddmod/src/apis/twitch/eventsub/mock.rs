@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::Json;
+use axum::Router;
+use futures_util::SinkExt;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+
+use crate::apis::twitch::eventsub::CreateSubscriptionBody;
+use crate::apis::twitch::eventsub::Subscription;
+
+const SESSION_ID: &str = "mock_session_id";
+
+/// Local stand-in for the EventSub WebSocket and the Helix subscriptions endpoint.
+///
+/// Useful to exercise [`super::EventSubClient`] and the event handlers without Twitch.
+pub struct MockEventSubServer {
+    pub ws_url: Url,
+    pub helix_url: Url,
+    notifications: broadcast::Sender<String>,
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+}
+
+impl MockEventSubServer {
+    pub async fn start() -> anyhow::Result<Self> {
+        let (notifications, _) = broadcast::channel(100);
+        let subscriptions = Arc::new(Mutex::new(vec![]));
+
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let ws_url = Url::parse(&format!("ws://{}/ws", ws_listener.local_addr()?))?;
+        let ws_notifications = notifications.clone();
+        tokio::spawn(async move {
+            while let Ok((tcp_stream, _)) = ws_listener.accept().await {
+                let mut notifications = ws_notifications.subscribe();
+                tokio::spawn(async move {
+                    let mut ws_stream = match tokio_tungstenite::accept_async(tcp_stream).await {
+                        Ok(ws_stream) => ws_stream,
+                        Err(error) => {
+                            eprintln!("Mock EventSub handshake error, error: {:?}", error);
+                            return;
+                        }
+                    };
+                    if ws_stream.send(Message::Text(welcome_message())).await.is_err() {
+                        return;
+                    }
+                    while let Ok(notification) = notifications.recv().await {
+                        if ws_stream.send(Message::Text(notification)).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        let helix_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let helix_url = Url::parse(&format!("http://{}/helix", helix_listener.local_addr()?))?;
+        let app = Router::new()
+            .route("/helix/eventsub/subscriptions", post(create_subscription))
+            .with_state(subscriptions.clone());
+        tokio::spawn(async move {
+            if let Err(error) = axum::serve(helix_listener, app.into_make_service()).await {
+                eprintln!("Mock Helix server error, error: {:?}", error);
+            }
+        });
+
+        Ok(Self {
+            ws_url,
+            helix_url,
+            notifications,
+            subscriptions,
+        })
+    }
+
+    /// Sends a notification to every connected client.
+    pub fn notify(&self, subscription_type: &str, version: &str, event: serde_json::Value) -> anyhow::Result<()> {
+        let notification = json!({
+            "metadata": {
+                "message_id": uuid(),
+                "message_type": "notification",
+                "message_timestamp": chrono::Utc::now(),
+                "subscription_type": subscription_type,
+                "subscription_version": version
+            },
+            "payload": {
+                "subscription": {
+                    "id": uuid(),
+                    "status": "enabled",
+                    "type": subscription_type,
+                    "version": version,
+                    "transport": { "method": "websocket", "session_id": SESSION_ID }
+                },
+                "event": event
+            }
+        });
+
+        self.notifications
+            .send(notification.to_string())
+            .map_err(|_| anyhow!("No client connected to mock EventSub server"))?;
+
+        Ok(())
+    }
+
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        self.subscriptions.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub async fn wait_for_subscriptions(&self, count: usize) -> anyhow::Result<()> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while self.subscriptions().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .map_err(|_| anyhow!("Expected {} subscriptions, got {:?}", count, self.subscriptions()))
+    }
+}
+
+async fn create_subscription(
+    State(subscriptions): State<Arc<Mutex<Vec<Subscription>>>>,
+    Json(body): Json<CreateSubscriptionBody>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if body.transport.session_id.as_deref() != Some(SESSION_ID) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Bad Request", "status": 400, "message": "invalid session_id" })),
+        );
+    }
+
+    let response = json!({
+        "data": [{
+            "id": uuid(),
+            "status": "enabled",
+            "type": body.subscription.kind,
+            "version": body.subscription.version,
+            "condition": body.subscription.condition,
+            "transport": body.transport,
+            "cost": 0
+        }],
+        "total": 1,
+        "total_cost": 0,
+        "max_total_cost": 10
+    });
+
+    match subscriptions.lock() {
+        Ok(mut subscriptions) => subscriptions.push(body.subscription),
+        Err(error) => eprintln!("Cannot get mock subscriptions Lock, error: {:?}", error),
+    }
+
+    (StatusCode::ACCEPTED, Json(response))
+}
+
+fn welcome_message() -> String {
+    json!({
+        "metadata": {
+            "message_id": uuid(),
+            "message_type": "session_welcome",
+            "message_timestamp": chrono::Utc::now()
+        },
+        "payload": {
+            "session": {
+                "id": SESSION_ID,
+                "status": "connected",
+                "connected_at": chrono::Utc::now(),
+                "keepalive_timeout_seconds": 10,
+                "reconnect_url": null
+            }
+        }
+    })
+    .to_string()
+}
+
+fn uuid() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
@@ -0,0 +1,25 @@
+use twitch_api::helix::streams::CreateStreamMarkerBody;
+use twitch_api::helix::streams::CreateStreamMarkerRequest;
+use twitch_api::helix::streams::CreatedStreamMarker;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+/// Twitch truncates longer descriptions.
+pub const MAX_DESCRIPTION_LEN: usize = 140;
+
+/// Marks the current position of the live stream of the broadcaster.
+pub async fn create_stream_marker(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    description: &str,
+) -> anyhow::Result<CreatedStreamMarker> {
+    let description: String = description.chars().take(MAX_DESCRIPTION_LEN).collect();
+    let body = CreateStreamMarkerBody::new(broadcaster_id, description);
+
+    Ok(helix_client
+        .req_post(CreateStreamMarkerRequest::new(), body, token)
+        .await?
+        .data)
+}
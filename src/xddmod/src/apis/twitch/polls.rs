@@ -0,0 +1,56 @@
+use twitch_api::helix::polls::create_poll::CreatePollBody;
+use twitch_api::helix::polls::create_poll::CreatePollRequest;
+use twitch_api::helix::polls::create_poll::NewPollChoice;
+use twitch_api::helix::polls::end_poll::EndPollBody;
+use twitch_api::helix::polls::end_poll::EndPollRequest;
+use twitch_api::helix::polls::get_polls::GetPollsRequest;
+use twitch_api::helix::polls::Poll;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::PollStatus;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+pub async fn create_poll(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    title: &str,
+    choices: &[String],
+    duration_secs: i64,
+) -> anyhow::Result<()> {
+    let new_choices: Vec<NewPollChoice> = choices.iter().map(|c| NewPollChoice::new(c.as_str())).collect();
+    let body = CreatePollBody::new(broadcaster_id, title, duration_secs, new_choices);
+
+    helix_client.req_post(CreatePollRequest::new(), body, token).await?;
+
+    Ok(())
+}
+
+/// Most recent poll of the broadcaster, whatever its status.
+pub async fn get_last_poll(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+) -> anyhow::Result<Option<Poll>> {
+    let poll_request = GetPollsRequest::builder()
+        .broadcaster_id(broadcaster_id.clone())
+        .first(Some(1))
+        .build();
+
+    Ok(helix_client.req_get(poll_request, token).await?.data.into_iter().next())
+}
+
+/// Terminates (showing the results) or archives the given poll.
+pub async fn end_poll(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    poll: &Poll,
+    status: PollStatus,
+) -> anyhow::Result<()> {
+    let body = EndPollBody::new(broadcaster_id, &poll.id, status);
+
+    helix_client.req_patch(EndPollRequest::new(), body, token).await?;
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use twitch_api::helix::predictions::create_prediction::CreatePredictionBody;
+use twitch_api::helix::predictions::create_prediction::CreatePredictionRequest;
+use twitch_api::helix::predictions::create_prediction::NewPredictionOutcome;
+use twitch_api::helix::predictions::end_prediction::EndPredictionBody;
+use twitch_api::helix::predictions::end_prediction::EndPredictionRequest;
+use twitch_api::helix::predictions::get_predictions::GetPredictionsRequest;
+use twitch_api::helix::predictions::Prediction;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::PredictionStatus;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+pub async fn create_prediction(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    title: &str,
+    outcomes: &[String],
+    window_secs: i64,
+) -> anyhow::Result<()> {
+    let new_outcomes: Vec<NewPredictionOutcome> =
+        outcomes.iter().map(|o| NewPredictionOutcome::new(o.as_str())).collect();
+    let body = CreatePredictionBody::new(broadcaster_id, title, new_outcomes, window_secs);
+
+    helix_client
+        .req_post(CreatePredictionRequest::new(), body, token)
+        .await?;
+
+    Ok(())
+}
+
+/// Most recent prediction of the broadcaster, whatever its status.
+pub async fn get_last_prediction(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+) -> anyhow::Result<Option<Prediction>> {
+    let prediction_request = GetPredictionsRequest::builder()
+        .broadcaster_id(broadcaster_id.clone())
+        .first(Some(1))
+        .build();
+
+    Ok(helix_client
+        .req_get(prediction_request, token)
+        .await?
+        .data
+        .into_iter()
+        .next())
+}
+
+/// Locks, resolves or cancels the given prediction.
+pub async fn end_prediction(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    prediction: &Prediction,
+    status: PredictionStatus,
+    winning_outcome_id: Option<String>,
+) -> anyhow::Result<()> {
+    let body = EndPredictionBody::builder()
+        .broadcaster_id(broadcaster_id)
+        .id(&prediction.id)
+        .status(status)
+        .winning_outcome_id(winning_outcome_id.map(Into::into))
+        .build();
+
+    helix_client.req_patch(EndPredictionRequest::new(), body, token).await?;
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use twitch_api::helix::points::CustomRewardRedemptionStatus;
+use twitch_api::helix::points::UpdateRedemptionStatusBody;
+use twitch_api::helix::points::UpdateRedemptionStatusRequest;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+/// Marks the redemption as fulfilled or canceled, refunding the points in the latter case.
+///
+/// Only works for redemptions of rewards created with the same client id.
+pub async fn update_redemption_status(
+    helix_client: &HelixClient<'_, reqwest::Client>,
+    token: &UserToken,
+    broadcaster_id: &UserId,
+    reward_id: &str,
+    redemption_id: &str,
+    status: CustomRewardRedemptionStatus,
+) -> anyhow::Result<()> {
+    let request = UpdateRedemptionStatusRequest::new(broadcaster_id, reward_id, redemption_id);
+    let body = UpdateRedemptionStatusBody::status(status);
+
+    helix_client.req_patch(request, body, token).await?;
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use std::net::Ipv4Addr;
 use std::net::SocketAddr;
 
 use config::Config;
@@ -7,6 +8,9 @@ use twitch_api::twitch_oauth2::ClientId;
 use twitch_api::twitch_oauth2::ClientSecret;
 use url::Url;
 
+use crate::apis::twitch::eventsub::EVENTSUB_WS_URL;
+use crate::apis::twitch::eventsub::HELIX_API_URL;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub socket_addr: SocketAddr,
@@ -17,6 +21,13 @@ pub struct AppConfig {
     pub db_url: Url,
     pub client_id: ClientId,
     pub client_secret: ClientSecret,
+    #[serde(default = "default_eventsub_ws_url")]
+    pub eventsub_ws_url: Url,
+    #[serde(default = "default_helix_api_url")]
+    pub helix_api_url: Url,
+    /// Kept off the public `socket_addr` of the auth callback server.
+    #[serde(default = "default_stats_socket_addr")]
+    pub stats_socket_addr: SocketAddr,
 }
 
 impl AppConfig {
@@ -29,3 +40,15 @@ impl AppConfig {
             .unwrap()
     }
 }
+
+fn default_eventsub_ws_url() -> Url {
+    Url::parse(EVENTSUB_WS_URL).unwrap()
+}
+
+fn default_helix_api_url() -> Url {
+    Url::parse(HELIX_API_URL).unwrap()
+}
+
+fn default_stats_socket_addr() -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, 9002))
+}
@@ -45,8 +45,14 @@ pub async fn authenticate(app_config: AppConfig) -> (MessageReceiver, IRCClient,
     .set_scopes(vec![
         Scope::ChatRead,
         Scope::ChatEdit,
-        Scope::ChannelReadPredictions,
+        Scope::ChannelManagePredictions,
+        Scope::ChannelManagePolls,
+        Scope::ChannelManageRedemptions,
+        Scope::ChannelManageBroadcast,
+        Scope::ClipsEdit,
+        Scope::ModeratorReadFollowers,
         Scope::ModeratorManageBannedUsers,
+        Scope::ModeratorManageShoutouts,
         Scope::parse("moderator:manage:chat_messages"),
     ]);
 
@@ -1,7 +1,27 @@
+pub mod auto_gamba;
+pub mod channel_info_commands;
+pub mod chat_activity;
+pub mod chat_events;
+pub mod counters;
+pub mod followage;
+pub mod gamba_commands;
+pub mod gamba_stats;
 pub mod gamba_time;
 pub mod gg;
 pub mod npc;
 pub mod persistence;
+pub mod points;
+pub mod poll_commands;
+pub mod poll_time;
+pub mod quotes;
+pub mod raffles;
+pub mod redemptions;
+pub mod reminders;
 pub mod rip_bozo;
+pub mod shoutout;
 pub mod sniffa;
+pub mod stream_markers;
 pub mod the_grind;
+pub mod timers;
+pub mod trivia;
+pub mod vars;
@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::SqlitePool;
+use twitch_api::helix::predictions::Prediction;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::PredictionStatus;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+use crate::apis::op_gg;
+use crate::apis::op_gg::games::Game;
+use crate::apis::op_gg::spectate::InGame;
+use crate::apis::op_gg::spectate::SpectateStatus;
+use crate::apis::twitch::predictions;
+use crate::handlers::auto_gamba::persistence::AutoGamba;
+
+pub const POLLING_INTERVAL: Duration = Duration::from_secs(60);
+/// Ranked Solo/Duo and Ranked Flex.
+pub const RANKED_QUEUE_IDS: [i64; 2] = [420, 440];
+// Tolerance between the spectate and the match history start of the same game.
+const GAME_START_TOLERANCE_MINS: i64 = 2;
+// Give up waiting for the match history after this and refund the prediction.
+const MAX_GAME_TRACKING_HOURS: i64 = 2;
+
+/// Opt-in predictions tied to the League games of the summoner configured for the channel.
+///
+/// Starts a prediction when the summoner enters a ranked game, letting Twitch lock it after `lock_after_secs`, and
+/// resolves it once the game shows up in the match history, refunding remakes.
+pub struct AutoGambas<'a> {
+    pub channel: String,
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub db_pool: SqlitePool,
+    tracked_game: Option<TrackedGame>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TrackedGame {
+    game_id: String,
+    started_at: DateTime<Utc>,
+    tracked_since: DateTime<Utc>,
+}
+
+impl<'a> AutoGambas<'a> {
+    pub fn new(
+        channel: String,
+        token: UserToken,
+        broadcaster_id: UserId,
+        helix_client: HelixClient<'a, reqwest::Client>,
+        db_pool: SqlitePool,
+    ) -> Self {
+        Self {
+            channel,
+            token,
+            broadcaster_id,
+            helix_client,
+            db_pool,
+            tracked_game: None,
+        }
+    }
+}
+
+impl AutoGambas<'_> {
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(POLLING_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.tick().await {
+                eprintln!(
+                    "Error managing auto gamba for channel {:?}, error: {:?}",
+                    self.channel, error
+                );
+            }
+        }
+    }
+
+    async fn tick(&mut self) -> anyhow::Result<()> {
+        let Some(auto_gamba) = AutoGamba::enabled_by_channel(&self.channel, &self.db_pool).await? else {
+            self.tracked_game = None;
+            return Ok(());
+        };
+
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for auto gamba");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        let region = auto_gamba.region()?;
+        let summoner = op_gg::summoners::get_summoner(region, &auto_gamba.summoner_name).await?;
+        let summoner_id = summoner.common.summoner_id;
+        let spectate_status = op_gg::spectate::get_spectate_status(region, &summoner_id).await?;
+
+        match (self.tracked_game.clone(), spectate_status) {
+            (None, SpectateStatus::InGame(InGame { data: game })) if RANKED_QUEUE_IDS.contains(&game.queue_info.id) => {
+                if let Some(prediction) = self.running_prediction().await? {
+                    eprintln!("Skip auto gamba, prediction already running {:?}", prediction);
+                    return Ok(());
+                }
+
+                predictions::create_prediction(
+                    &self.helix_client,
+                    &self.token,
+                    &self.broadcaster_id,
+                    &auto_gamba.title,
+                    &[auto_gamba.win_outcome.clone(), auto_gamba.lose_outcome.clone()],
+                    auto_gamba.lock_after_secs.clamp(30, 1800),
+                )
+                .await?;
+
+                self.tracked_game = Some(TrackedGame {
+                    game_id: game.game_id,
+                    started_at: game.created_at,
+                    tracked_since: Utc::now(),
+                });
+            }
+            (None, _) => {}
+            (Some(tracked_game), SpectateStatus::InGame(InGame { data: game }))
+                if game.game_id == tracked_game.game_id => {}
+            (Some(tracked_game), _) => {
+                let last_game = op_gg::games::get_last_game(region, &summoner_id).await?.filter(|game| {
+                    game.created_at >= tracked_game.started_at - chrono::Duration::minutes(GAME_START_TOLERANCE_MINS)
+                });
+
+                let game_outcome = match last_game {
+                    Some(game) => GameOutcome::from(&game),
+                    None if Utc::now() - tracked_game.tracked_since
+                        > chrono::Duration::hours(MAX_GAME_TRACKING_HOURS) =>
+                    {
+                        GameOutcome::Remake
+                    }
+                    // Match history not updated yet
+                    None => return Ok(()),
+                };
+
+                self.tracked_game = None;
+                self.resolve(&auto_gamba, game_outcome).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prediction still active or locked, if any.
+    async fn running_prediction(&self) -> anyhow::Result<Option<Prediction>> {
+        Ok(
+            predictions::get_last_prediction(&self.helix_client, &self.token, &self.broadcaster_id)
+                .await?
+                .filter(|p| matches!(p.status, PredictionStatus::Active | PredictionStatus::Locked)),
+        )
+    }
+
+    async fn resolve(&self, auto_gamba: &AutoGamba, game_outcome: GameOutcome) -> anyhow::Result<()> {
+        let prediction = self
+            .running_prediction()
+            .await?
+            .filter(|p| p.title == auto_gamba.title)
+            .ok_or_else(|| anyhow!("No running auto gamba to resolve with {:?}", game_outcome))?;
+
+        let (status, winning_outcome_title) = match game_outcome {
+            GameOutcome::Win => (PredictionStatus::Resolved, Some(&auto_gamba.win_outcome)),
+            GameOutcome::Lose => (PredictionStatus::Resolved, Some(&auto_gamba.lose_outcome)),
+            GameOutcome::Remake => (PredictionStatus::Canceled, None),
+        };
+
+        let winning_outcome_id = match winning_outcome_title {
+            Some(title) => Some(
+                prediction
+                    .outcomes
+                    .iter()
+                    .find(|o| &o.title == title)
+                    .map(|o| o.id.to_string())
+                    .ok_or_else(|| anyhow!("No outcome {:?} in prediction {:?}", title, prediction))?,
+            ),
+            None => None,
+        };
+
+        predictions::end_prediction(
+            &self.helix_client,
+            &self.token,
+            &self.broadcaster_id,
+            &prediction,
+            status,
+            winning_outcome_id,
+        )
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameOutcome {
+    Win,
+    Lose,
+    Remake,
+}
+
+impl From<&Game> for GameOutcome {
+    fn from(game: &Game) -> Self {
+        if game.is_remake {
+            return Self::Remake;
+        }
+
+        match game.teams.iter().find(|t| t.key == game.my_data.team_key) {
+            Some(team) if team.game_stat.is_remake => Self::Remake,
+            Some(team) if team.game_stat.is_win => Self::Win,
+            Some(_) => Self::Lose,
+            None if game.my_data.stats.result.eq_ignore_ascii_case("win") => Self::Win,
+            None => Self::Lose,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::Fake;
+    use fake::Faker;
+
+    use super::*;
+    use crate::apis::op_gg::games::Team;
+    use crate::apis::op_gg::TeamKey;
+
+    #[test]
+    fn test_game_outcome_from_game_works_as_expected() {
+        let mut game: Game = Faker.fake();
+        game.is_remake = false;
+        game.my_data.team_key = TeamKey::Blue;
+        game.my_data.stats.result = "LOSE".into();
+
+        let mut blue_team = Faker.fake::<Team>();
+        blue_team.key = TeamKey::Blue;
+        blue_team.game_stat.is_remake = false;
+        blue_team.game_stat.is_win = true;
+        game.teams = vec![blue_team.clone()];
+        assert_eq!(GameOutcome::Win, GameOutcome::from(&game));
+
+        blue_team.game_stat.is_win = false;
+        game.teams = vec![blue_team.clone()];
+        assert_eq!(GameOutcome::Lose, GameOutcome::from(&game));
+
+        blue_team.game_stat.is_remake = true;
+        game.teams = vec![blue_team];
+        assert_eq!(GameOutcome::Remake, GameOutcome::from(&game));
+
+        game.teams = vec![];
+        assert_eq!(GameOutcome::Lose, GameOutcome::from(&game));
+        game.my_data.stats.result = "WIN".into();
+        assert_eq!(GameOutcome::Win, GameOutcome::from(&game));
+
+        game.is_remake = true;
+        assert_eq!(GameOutcome::Remake, GameOutcome::from(&game));
+    }
+}
@@ -0,0 +1,56 @@
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+use crate::apis::op_gg::Region;
+
+#[derive(Debug, Clone)]
+pub struct AutoGamba {
+    pub channel: String,
+    pub region: String,
+    pub summoner_name: String,
+    pub title: String,
+    pub win_outcome: String,
+    pub lose_outcome: String,
+    pub lock_after_secs: i64,
+    pub enabled: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AutoGamba {
+    pub fn region(&self) -> anyhow::Result<Region> {
+        Ok(serde_json::from_value(serde_json::Value::String(
+            self.region.to_lowercase(),
+        ))?)
+    }
+
+    pub async fn enabled_by_channel<'a>(
+        channel: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    region,
+                    summoner_name,
+                    title,
+                    win_outcome,
+                    lose_outcome,
+                    lock_after_secs,
+                    enabled,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from auto_gambas
+                where channel = $1 and enabled = 1
+            "#,
+            channel,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+}
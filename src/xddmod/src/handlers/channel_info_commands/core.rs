@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::apis::twitch::channels;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Mod commands to change the stream title and category, e.g. `!title <text>` and `!game <name>`.
+///
+/// The reply pattern must capture the `action` (`title` or `game`) and its `value`.
+/// The category is resolved searching its name: when the match is ambiguous the category is left untouched and the
+/// closest categories are passed to the template as `suggestions`.
+///
+/// Requires the broadcaster token with the `channel:manage:broadcast` scope.
+pub struct ChannelInfoCommands<'a> {
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl ChannelInfoCommands<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::ChannelInfoCommands
+    }
+}
+
+impl ChannelInfoCommands<'_> {
+    pub async fn handle(&mut self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let channel_info_command = match ChannelInfoCommand::try_from(&reply.captures(message)) {
+                        Ok(channel_info_command) => channel_info_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing channel info command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(&channel_info_command).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!(
+                                "Error executing channel info command {:?}, error: {:?}.",
+                                channel_info_command, error
+                            );
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn execute(&mut self, channel_info_command: &ChannelInfoCommand) -> anyhow::Result<TemplateInputs> {
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for channel info commands");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        let mut template_inputs = TemplateInputs {
+            action: channel_info_command.action(),
+            title: None,
+            category: None,
+            suggestions: vec![],
+        };
+
+        match channel_info_command {
+            ChannelInfoCommand::Title { title } => {
+                channels::modify_channel_information(
+                    &self.helix_client,
+                    &self.token,
+                    &self.broadcaster_id,
+                    Some(title),
+                    None,
+                )
+                .await?;
+
+                template_inputs.title = Some(title.clone());
+            }
+            ChannelInfoCommand::Game { name } => {
+                let categories = channels::search_categories(&self.helix_client, &self.token, name).await?;
+                let names: Vec<String> = categories.iter().map(|c| c.name.clone()).collect();
+
+                match CategoryMatch::resolve(name, &names) {
+                    CategoryMatch::Found(idx) => {
+                        let category = &categories[idx];
+                        channels::modify_channel_information(
+                            &self.helix_client,
+                            &self.token,
+                            &self.broadcaster_id,
+                            None,
+                            Some(&category.id),
+                        )
+                        .await?;
+
+                        template_inputs.category = Some(category.name.clone());
+                    }
+                    CategoryMatch::Ambiguous(suggestions) => template_inputs.suggestions = suggestions,
+                    CategoryMatch::NotFound => {}
+                }
+            }
+        }
+
+        Ok(template_inputs)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ChannelInfoCommand {
+    Title { title: String },
+    Game { name: String },
+}
+
+impl ChannelInfoCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Title { .. } => "title",
+            Self::Game { .. } => "game",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for ChannelInfoCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "title" => Self::Title {
+                title: required_capture(captures, "value")?.into(),
+            },
+            "game" | "category" => Self::Game {
+                name: required_capture(captures, "value")?.into(),
+            },
+            unexpected_action => bail!("Unexpected channel info action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CategoryMatch {
+    /// Index of the matching category.
+    Found(usize),
+    Ambiguous(Vec<String>),
+    NotFound,
+}
+
+impl CategoryMatch {
+    /// Picks the category named as the query, ignoring case and punctuation, or the only searched one if it
+    /// starts with or contains the query. Otherwise suggests the categories starting with, then containing, the query,
+    /// keeping the search ranking.
+    pub fn resolve(query: &str, names: &[String]) -> Self {
+        let query = normalize(query);
+        let normalized_names: Vec<String> = names.iter().map(|n| normalize(n)).collect();
+
+        if let Some(idx) = normalized_names.iter().position(|n| *n == query) {
+            return Self::Found(idx);
+        }
+
+        match names {
+            [] => Self::NotFound,
+            [_] if normalized_names[0].contains(&query) => Self::Found(0),
+            [name] => Self::Ambiguous(vec![name.clone()]),
+            _ => {
+                let mut ranked: Vec<(usize, &String)> = normalized_names
+                    .iter()
+                    .map(|n| match (n.starts_with(&query), n.contains(&query)) {
+                        (true, _) => 0,
+                        (false, true) => 1,
+                        (false, false) => 2,
+                    })
+                    .zip(names)
+                    .collect();
+                ranked.sort_by_key(|(rank, _)| *rank);
+
+                Self::Ambiguous(
+                    ranked
+                        .into_iter()
+                        .take(MAX_SUGGESTIONS)
+                        .map(|(_, name)| name.clone())
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    title: Option<String>,
+    /// The new category, if resolved.
+    category: Option<String>,
+    suggestions: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_channel_info_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            ChannelInfoCommand::Title {
+                title: "Road to Challenger".into()
+            },
+            ChannelInfoCommand::try_from(&test_captures(&[
+                ("action", "title"),
+                ("value", " Road to Challenger ")
+            ]))
+            .unwrap()
+        );
+        assert_eq!(
+            ChannelInfoCommand::Game {
+                name: "League of Legends".into()
+            },
+            ChannelInfoCommand::try_from(&test_captures(&[("action", "Game"), ("value", "League of Legends")]))
+                .unwrap()
+        );
+        assert!(ChannelInfoCommand::try_from(&test_captures(&[("action", "title"), ("value", " ")])).is_err());
+        assert!(ChannelInfoCommand::try_from(&test_captures(&[("action", "foo"), ("value", "bar")])).is_err());
+    }
+
+    #[test]
+    fn test_category_match_resolve_works_as_expected() {
+        let names = |xs: &[&str]| -> Vec<String> { xs.iter().map(|x| x.to_string()).collect() };
+
+        assert_eq!(
+            CategoryMatch::Found(1),
+            CategoryMatch::resolve(
+                "league of legends",
+                &names(&["Legends of Runeterra", "League of Legends", "Apex Legends"])
+            )
+        );
+        assert_eq!(
+            CategoryMatch::Found(0),
+            CategoryMatch::resolve("teamfight", &names(&["Teamfight Tactics"]))
+        );
+        assert_eq!(
+            CategoryMatch::Ambiguous(names(&["Teamfight Tactics"])),
+            CategoryMatch::resolve("tft", &names(&["Teamfight Tactics"]))
+        );
+        assert_eq!(
+            CategoryMatch::Ambiguous(names(&["Dark Souls", "Dark Souls III", "Demon's Souls"])),
+            CategoryMatch::resolve(
+                "dark",
+                &names(&["Demon's Souls", "Dark Souls", "Dark Souls III", "Elden Ring"])
+            )
+        );
+        assert_eq!(CategoryMatch::NotFound, CategoryMatch::resolve("foo", &[]));
+    }
+}
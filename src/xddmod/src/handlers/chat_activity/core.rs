@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
+use chrono_tz::Tz;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::Emote;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::handlers::chat_activity::persistence::EmoteUsage;
+use crate::handlers::chat_activity::persistence::StreamChatters;
+use crate::handlers::chat_activity::persistence::UserActivity;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::rip_bozo::core::target_login;
+use crate::outbox::Outbox;
+use crate::streams::StreamStatus;
+use crate::streams::Streams;
+
+/// Window used to compute the messages per minute.
+pub const RATE_WINDOW_SECS: i64 = 300;
+pub const TOP_LEN: i64 = 5;
+
+/// Tracks the chat activity of users and channels, e.g. for `!lastseen @user` and `!top`.
+///
+/// Every message updates the activity of its sender, the chatters of the live stream and the emotes usage.
+/// The reply pattern must capture the `action` (`lastseen` or `top`).
+/// `lastseen` gets the `activity` of the first mentioned user or the sender, `top` the channel `stats`.
+/// The same stats are served as JSON by [`crate::stats_server`].
+pub struct ChatActivity<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+    recent_messages: Mutex<HashMap<String, VecDeque<DateTime<Utc>>>>,
+}
+
+impl<'a> ChatActivity<'a> {
+    pub fn new(outbox: Outbox, streams: Streams, db_pool: SqlitePool, templates_env: Environment<'a>) -> Self {
+        Self {
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            recent_messages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn handler(&self) -> Handler {
+        Handler::ChatActivity
+    }
+}
+
+impl ChatActivity<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message) = server_message {
+            self.track(message, channel.tz(), Utc::now()).await;
+            if message.is_action {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let activity_command = match ActivityCommand::try_from(&reply.captures(message)) {
+                        Ok(activity_command) => activity_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing activity command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(message, &activity_command).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!(
+                                "Error executing activity command {:?}, error: {:?}.",
+                                activity_command, error
+                            );
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    /// Messages rate, chatters and top chatters and emotes of the channel.
+    pub async fn channel_stats(&self, channel: &str) -> anyhow::Result<ChannelStats> {
+        let stream = match self.streams.get(channel) {
+            Some(StreamStatus::Live(live_stream)) => {
+                Some(StreamChatters::by_stream(channel, live_stream.started_at, &self.db_pool).await?)
+            }
+            _ => None,
+        };
+
+        Ok(ChannelStats {
+            channel: channel.into(),
+            messages_per_minute: self.messages_per_minute(channel, Utc::now()),
+            stream,
+            chatters: UserActivity::count_by_channel(channel, &self.db_pool).await?,
+            top_chatters: UserActivity::top_by_channel(channel, TOP_LEN, &self.db_pool).await?,
+            top_emotes: EmoteUsage::top_by_channel(channel, TOP_LEN, &self.db_pool).await?,
+        })
+    }
+
+    pub async fn user_activity(&self, channel: &str, user_login: &str) -> anyhow::Result<Option<UserActivity>> {
+        Ok(UserActivity::get(channel, user_login, &self.db_pool).await?)
+    }
+
+    async fn track(&self, message: &PrivmsgMessage, timezone: Tz, now: DateTime<Utc>) {
+        let channel = message.channel_login.as_str();
+
+        match self.recent_messages.lock() {
+            Ok(mut recent_messages) => record(recent_messages.entry(channel.into()).or_default(), now),
+            Err(error) => eprintln!("Cannot get recent messages Lock, error: {:?}", error),
+        }
+
+        if let Err(error) = UserActivity::track(
+            channel,
+            &message.sender.login,
+            &message.sender.name,
+            now,
+            timezone,
+            &self.db_pool,
+        )
+        .await
+        {
+            eprintln!("Error tracking user activity {:?}, error: {:?}", message.sender, error);
+        }
+
+        if let Some(StreamStatus::Live(live_stream)) = self.streams.get(channel) {
+            if let Err(error) =
+                StreamChatters::track(channel, live_stream.started_at, &message.sender.login, &self.db_pool).await
+            {
+                eprintln!("Error tracking stream chatter {:?}, error: {:?}", message.sender, error);
+            }
+        }
+
+        for (emote_id, (emote_code, uses)) in emotes_uses(&message.emotes) {
+            if let Err(error) = EmoteUsage::track(channel, emote_id, emote_code, uses, now, &self.db_pool).await {
+                eprintln!("Error tracking emote usage {:?}, error: {:?}", emote_code, error);
+            }
+        }
+    }
+
+    async fn execute(
+        &self,
+        message: &PrivmsgMessage,
+        activity_command: &ActivityCommand,
+    ) -> anyhow::Result<TemplateInputs> {
+        let channel = message.channel_login.as_str();
+        let mut template_inputs = TemplateInputs {
+            action: activity_command.action(),
+            user: message.sender.name.clone(),
+            target: None,
+            activity: None,
+            stats: None,
+        };
+
+        match activity_command {
+            ActivityCommand::LastSeen => {
+                let target_login = target_login(message).to_lowercase();
+                template_inputs.activity = self.user_activity(channel, &target_login).await?;
+                template_inputs.target = Some(target_login);
+            }
+            ActivityCommand::Top => template_inputs.stats = Some(self.channel_stats(channel).await?),
+        }
+
+        Ok(template_inputs)
+    }
+
+    fn messages_per_minute(&self, channel: &str, now: DateTime<Utc>) -> f64 {
+        match self.recent_messages.lock() {
+            Ok(mut recent_messages) => recent_messages
+                .get_mut(channel)
+                .map(|timestamps| rate(timestamps, now))
+                .unwrap_or_default(),
+            Err(error) => {
+                eprintln!("Cannot get recent messages Lock, error: {:?}", error);
+                0.0
+            }
+        }
+    }
+}
+
+fn record(timestamps: &mut VecDeque<DateTime<Utc>>, now: DateTime<Utc>) {
+    timestamps.push_back(now);
+    evict(timestamps, now);
+}
+
+fn rate(timestamps: &mut VecDeque<DateTime<Utc>>, now: DateTime<Utc>) -> f64 {
+    evict(timestamps, now);
+    timestamps.len() as f64 * 60.0 / RATE_WINDOW_SECS as f64
+}
+
+fn evict(timestamps: &mut VecDeque<DateTime<Utc>>, now: DateTime<Utc>) {
+    while timestamps
+        .front()
+        .is_some_and(|t| (now - *t).num_seconds() >= RATE_WINDOW_SECS)
+    {
+        timestamps.pop_front();
+    }
+}
+
+/// Uses of each emote of the message by emote id, with its code.
+fn emotes_uses(emotes: &[Emote]) -> HashMap<&str, (&str, i64)> {
+    let mut uses: HashMap<&str, (&str, i64)> = HashMap::new();
+    for emote in emotes {
+        uses.entry(emote.id.as_str()).or_insert((emote.code.as_str(), 0)).1 += 1;
+    }
+    uses
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChannelStats {
+    pub channel: String,
+    /// Over the last [`RATE_WINDOW_SECS`].
+    pub messages_per_minute: f64,
+    /// Chatters of the current stream, if live.
+    pub stream: Option<StreamChatters>,
+    /// Users ever seen in chat.
+    pub chatters: i64,
+    pub top_chatters: Vec<UserActivity>,
+    pub top_emotes: Vec<EmoteUsage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActivityCommand {
+    LastSeen,
+    Top,
+}
+
+impl ActivityCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::LastSeen => "lastseen",
+            Self::Top => "top",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for ActivityCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "lastseen" | "seen" => Self::LastSeen,
+            "top" | "chatters" => Self::Top,
+            unexpected_action => bail!("Unexpected activity action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    user: String,
+    target: Option<String>,
+    /// Empty if the target was never seen in chat.
+    activity: Option<UserActivity>,
+    stats: Option<ChannelStats>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_activity_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            ActivityCommand::LastSeen,
+            ActivityCommand::try_from(&test_captures(&[("action", "LastSeen")])).unwrap()
+        );
+        assert_eq!(
+            ActivityCommand::Top,
+            ActivityCommand::try_from(&test_captures(&[("action", " top ")])).unwrap()
+        );
+        assert!(ActivityCommand::try_from(&test_captures(&[("action", "foo")])).is_err());
+        assert!(ActivityCommand::try_from(&test_captures(&[])).is_err());
+    }
+
+    #[test]
+    fn test_rate_works_as_expected() {
+        let now = Utc::now();
+        let mut timestamps = VecDeque::new();
+
+        record(&mut timestamps, now - chrono::Duration::seconds(RATE_WINDOW_SECS + 1));
+        for secs in [120, 60, 0] {
+            record(&mut timestamps, now - chrono::Duration::seconds(secs));
+        }
+
+        assert_eq!(3, timestamps.len());
+        assert_eq!(3.0 * 60.0 / RATE_WINDOW_SECS as f64, rate(&mut timestamps, now));
+        assert_eq!(
+            0.0,
+            rate(&mut timestamps, now + chrono::Duration::seconds(RATE_WINDOW_SECS))
+        );
+        assert!(timestamps.is_empty());
+    }
+
+    #[test]
+    fn test_emotes_uses_works_as_expected() {
+        let emote = |id: &str, code: &str, start: usize| Emote {
+            id: id.into(),
+            char_range: start..start + code.len(),
+            code: code.into(),
+        };
+        let emotes = vec![emote("1", "Kappa", 0), emote("2", "LUL", 6), emote("1", "Kappa", 10)];
+
+        let uses = emotes_uses(&emotes);
+
+        assert_eq!(2, uses.len());
+        assert_eq!(Some(&("Kappa", 2)), uses.get("1"));
+        assert_eq!(Some(&("LUL", 1)), uses.get("2"));
+        assert!(emotes_uses(&[]).is_empty());
+    }
+}
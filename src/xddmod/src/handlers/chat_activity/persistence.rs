@@ -0,0 +1,254 @@
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::NaiveDate;
+use sqlx::types::chrono::Utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserActivity {
+    pub channel: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub messages_count: i64,
+    /// Distinct days, in the channel timezone, with at least a message.
+    pub active_days: i64,
+    pub last_active_day: NaiveDate,
+}
+
+/// Chatters of a single stream, identified by its start.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StreamChatters {
+    pub unique_chatters: i64,
+    pub messages_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmoteUsage {
+    pub channel: String,
+    pub emote_id: String,
+    pub emote_code: String,
+    pub uses: i64,
+    pub last_used_at: DateTime<Utc>,
+}
+
+impl UserActivity {
+    pub async fn track<'a>(
+        channel: &str,
+        user_login: &str,
+        user_name: &str,
+        seen_at: DateTime<Utc>,
+        timezone: Tz,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        let day = seen_at.with_timezone(&timezone).date_naive();
+
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into user_activities (
+                    channel,
+                    user_login,
+                    user_name,
+                    first_seen_at,
+                    last_seen_at,
+                    messages_count,
+                    active_days,
+                    last_active_day
+                )
+                values ($1, $2, $3, $4, $4, 1, 1, $5)
+                on conflict (channel, user_login) do update set
+                    user_name = excluded.user_name,
+                    last_seen_at = excluded.last_seen_at,
+                    messages_count = messages_count + 1,
+                    active_days = active_days + (last_active_day <> excluded.last_active_day),
+                    last_active_day = excluded.last_active_day
+                returning
+                    channel,
+                    user_login,
+                    user_name,
+                    first_seen_at as "first_seen_at!: DateTime<Utc>",
+                    last_seen_at as "last_seen_at!: DateTime<Utc>",
+                    messages_count,
+                    active_days,
+                    last_active_day as "last_active_day!: NaiveDate"
+            "#,
+            channel,
+            user_login,
+            user_name,
+            seen_at,
+            day,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn get<'a>(
+        channel: &str,
+        user_login: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    user_login,
+                    user_name,
+                    first_seen_at as "first_seen_at!: DateTime<Utc>",
+                    last_seen_at as "last_seen_at!: DateTime<Utc>",
+                    messages_count,
+                    active_days,
+                    last_active_day as "last_active_day!: NaiveDate"
+                from user_activities
+                where channel = $1 and user_login = $2
+            "#,
+            channel,
+            user_login,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn top_by_channel<'a>(
+        channel: &str,
+        limit: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    user_login,
+                    user_name,
+                    first_seen_at as "first_seen_at!: DateTime<Utc>",
+                    last_seen_at as "last_seen_at!: DateTime<Utc>",
+                    messages_count,
+                    active_days,
+                    last_active_day as "last_active_day!: NaiveDate"
+                from user_activities
+                where channel = $1
+                order by messages_count desc
+                limit $2
+            "#,
+            channel,
+            limit,
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn count_by_channel<'a>(channel: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"select count(*) as "count!: i64" from user_activities where channel = $1"#,
+            channel,
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
+
+impl StreamChatters {
+    pub async fn track<'a>(
+        channel: &str,
+        stream_started_at: DateTime<Utc>,
+        user_login: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+                insert into stream_chatters (channel, stream_started_at, user_login, messages_count)
+                values ($1, $2, $3, 1)
+                on conflict (channel, stream_started_at, user_login) do update set
+                    messages_count = messages_count + 1
+            "#,
+            channel,
+            stream_started_at,
+            user_login,
+        )
+        .execute(executor)
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn by_stream<'a>(
+        channel: &str,
+        stream_started_at: DateTime<Utc>,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    count(*) as "unique_chatters!: i64",
+                    coalesce(sum(messages_count), 0) as "messages_count!: i64"
+                from stream_chatters
+                where channel = $1 and stream_started_at = $2
+            "#,
+            channel,
+            stream_started_at,
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
+
+impl EmoteUsage {
+    pub async fn track<'a>(
+        channel: &str,
+        emote_id: &str,
+        emote_code: &str,
+        uses: i64,
+        used_at: DateTime<Utc>,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+                insert into emote_usages (channel, emote_id, emote_code, uses, last_used_at)
+                values ($1, $2, $3, $4, $5)
+                on conflict (channel, emote_id) do update set
+                    emote_code = excluded.emote_code,
+                    uses = uses + excluded.uses,
+                    last_used_at = excluded.last_used_at
+            "#,
+            channel,
+            emote_id,
+            emote_code,
+            uses,
+            used_at,
+        )
+        .execute(executor)
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn top_by_channel<'a>(
+        channel: &str,
+        limit: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    emote_id,
+                    emote_code,
+                    uses,
+                    last_used_at as "last_used_at!: DateTime<Utc>"
+                from emote_usages
+                where channel = $1
+                order by uses desc
+                limit $2
+            "#,
+            channel,
+            limit,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
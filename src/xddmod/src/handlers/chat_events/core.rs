@@ -0,0 +1,440 @@
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+use twitch_irc::message::UserNoticeEvent;
+use twitch_irc::message::UserNoticeMessage;
+
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::MatchableMessage;
+use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Templated responses to subs, resubs, gifts, raids, announcements and cheers.
+///
+/// Replies are matched against the event kind (e.g. `^resub$`) and rendered with the event fields.
+pub struct ChatEvents<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl ChatEvents<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::ChatEvents
+    }
+}
+
+impl ChatEvents<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        let (chat_event, reply_to) = match server_message {
+            ServerMessage::UserNotice(message) => match ChatEvent::from_user_notice(message) {
+                Some(chat_event) => (chat_event, None),
+                None => return,
+            },
+            ServerMessage::Privmsg(message @ PrivmsgMessage { bits: Some(bits), .. }) if *bits > 0 => {
+                (ChatEvent::from_cheer(message, *bits), Some(message))
+            }
+            _ => return,
+        };
+
+        let is_live = self.streams.is_live(&chat_event.channel);
+        match Reply::matching(self.handler(), &chat_event, channel, is_live, &self.db_pool)
+            .await
+            .as_slice()
+        {
+            [reply] => match reply
+                .render_template(
+                    &self.templates_env,
+                    channel,
+                    &self.streams,
+                    &self.db_pool,
+                    Some(&Value::from_serialize(&chat_event.kind)),
+                )
+                .await
+            {
+                Ok(rendered_reply) if rendered_reply.is_empty() => {
+                    eprintln!("Rendered reply template empty: {:?}.", reply)
+                }
+                Ok(rendered_reply) => match reply_to {
+                    Some(message) => self.outbox.say_in_reply_to(message, rendered_reply),
+                    None => self.outbox.say(&chat_event.channel, rendered_reply),
+                },
+                Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+            },
+            [] => {}
+            multiple_matching_replies => eprintln!(
+                "Multiple matching replies for chat event: {:?}, {:?}.",
+                multiple_matching_replies, chat_event
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChatEvent {
+    pub channel: String,
+    pub kind: ChatEventKind,
+}
+
+impl ChatEvent {
+    pub fn from_user_notice(message: &UserNoticeMessage) -> Option<Self> {
+        let user = message.sender.name.clone();
+        let text = message.message_text.clone();
+
+        let kind = match &message.event {
+            UserNoticeEvent::SubOrResub {
+                is_resub,
+                cumulative_months,
+                streak_months,
+                sub_plan,
+                ..
+            } => {
+                let sub = Sub {
+                    user,
+                    months: *cumulative_months,
+                    streak_months: *streak_months,
+                    tier: tier(sub_plan),
+                    message: text,
+                };
+                if *is_resub {
+                    ChatEventKind::Resub(sub)
+                } else {
+                    ChatEventKind::Sub(sub)
+                }
+            }
+            // Gifts that are part of a mystery gift are already covered by the SubMysteryGift event.
+            UserNoticeEvent::SubGift { .. } if message.source.tags.0.contains_key("msg-param-community-gift-id") => {
+                return None
+            }
+            UserNoticeEvent::SubGift {
+                is_sender_anonymous,
+                recipient,
+                sub_plan,
+                num_gifted_months,
+                ..
+            } => ChatEventKind::SubGift {
+                gifter: (!is_sender_anonymous).then_some(user),
+                recipient: recipient.name.clone(),
+                months: *num_gifted_months,
+                tier: tier(sub_plan),
+            },
+            UserNoticeEvent::SubMysteryGift {
+                mass_gift_count,
+                sender_total_gifts,
+                sub_plan,
+                ..
+            } => ChatEventKind::SubMysteryGift {
+                gifter: Some(user),
+                count: *mass_gift_count,
+                gifter_total: Some(*sender_total_gifts),
+                tier: tier(sub_plan),
+            },
+            UserNoticeEvent::AnonSubMysteryGift {
+                mass_gift_count,
+                sub_plan,
+                ..
+            } => ChatEventKind::SubMysteryGift {
+                gifter: None,
+                count: *mass_gift_count,
+                gifter_total: None,
+                tier: tier(sub_plan),
+            },
+            UserNoticeEvent::Raid { viewer_count, .. } => ChatEventKind::Raid {
+                raider: user,
+                viewer_count: *viewer_count,
+            },
+            _ if message.event_id == "announcement" => ChatEventKind::Announcement {
+                user,
+                message: text.unwrap_or_default(),
+            },
+            _ => return None,
+        };
+
+        Some(Self {
+            channel: message.channel_login.clone(),
+            kind,
+        })
+    }
+
+    pub fn from_cheer(message: &PrivmsgMessage, bits: u64) -> Self {
+        Self {
+            channel: message.channel_login.clone(),
+            kind: ChatEventKind::Cheer {
+                user: message.sender.name.clone(),
+                bits,
+                message: message.message_text.clone(),
+            },
+        }
+    }
+}
+
+impl MatchableMessage for ChatEvent {
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn text(&self) -> &str {
+        self.kind.as_str()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ChatEventKind {
+    Sub(Sub),
+    Resub(Sub),
+    SubGift {
+        gifter: Option<String>,
+        recipient: String,
+        months: u64,
+        tier: String,
+    },
+    SubMysteryGift {
+        gifter: Option<String>,
+        count: u64,
+        gifter_total: Option<u64>,
+        tier: String,
+    },
+    Raid {
+        raider: String,
+        viewer_count: u64,
+    },
+    Announcement {
+        user: String,
+        message: String,
+    },
+    Cheer {
+        user: String,
+        bits: u64,
+        message: String,
+    },
+}
+
+impl ChatEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sub(_) => "sub",
+            Self::Resub(_) => "resub",
+            Self::SubGift { .. } => "subgift",
+            Self::SubMysteryGift { .. } => "submysterygift",
+            Self::Raid { .. } => "raid",
+            Self::Announcement { .. } => "announcement",
+            Self::Cheer { .. } => "cheer",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Sub {
+    pub user: String,
+    pub months: u64,
+    pub streak_months: Option<u64>,
+    pub tier: String,
+    pub message: Option<String>,
+}
+
+fn tier(sub_plan: &str) -> String {
+    match sub_plan {
+        "1000" => "1".into(),
+        "2000" => "2".into(),
+        "3000" => "3".into(),
+        other => other.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use twitch_irc::message::IRCMessage;
+
+    use super::*;
+
+    #[test]
+    fn test_chat_event_kind_serializes_as_expected() {
+        assert_eq!(
+            json!({"kind": "resub", "user": "foo", "months": 3, "streak_months": null, "tier": "1", "message": null}),
+            serde_json::to_value(ChatEventKind::Resub(Sub {
+                user: "foo".into(),
+                months: 3,
+                streak_months: None,
+                tier: tier("1000"),
+                message: None,
+            }))
+            .unwrap()
+        );
+        assert_eq!(
+            json!({"kind": "raid", "raider": "foo", "viewer_count": 42}),
+            serde_json::to_value(ChatEventKind::Raid {
+                raider: "foo".into(),
+                viewer_count: 42
+            })
+            .unwrap()
+        );
+        assert_eq!("Prime", tier("Prime"));
+    }
+
+    #[test]
+    fn test_chat_event_from_user_notice_splits_subs_and_resubs() {
+        let sub = user_notice(
+            "foo",
+            "42",
+            "msg-id=sub;msg-param-cumulative-months=1;msg-param-should-share-streak=0;msg-param-sub-plan=Prime;msg-param-sub-plan-name=Channel\\sSubscription",
+            None,
+        );
+        assert_eq!(
+            Some(ChatEvent {
+                channel: "bar".into(),
+                kind: ChatEventKind::Sub(Sub {
+                    user: "foo".into(),
+                    months: 1,
+                    streak_months: None,
+                    tier: "Prime".into(),
+                    message: None,
+                })
+            }),
+            ChatEvent::from_user_notice(&sub)
+        );
+
+        let resub = user_notice(
+            "foo",
+            "42",
+            "msg-id=resub;msg-param-cumulative-months=3;msg-param-should-share-streak=1;msg-param-streak-months=2;msg-param-sub-plan=2000;msg-param-sub-plan-name=Channel\\sSubscription",
+            Some("Great stream"),
+        );
+        assert_eq!(
+            Some(ChatEvent {
+                channel: "bar".into(),
+                kind: ChatEventKind::Resub(Sub {
+                    user: "foo".into(),
+                    months: 3,
+                    streak_months: Some(2),
+                    tier: "2".into(),
+                    message: Some("Great stream".into()),
+                })
+            }),
+            ChatEvent::from_user_notice(&resub)
+        );
+    }
+
+    #[test]
+    fn test_chat_event_from_user_notice_skips_gifts_of_community_gifts() {
+        let mystery_gift = user_notice(
+            "foo",
+            "42",
+            "msg-id=submysterygift;msg-param-community-gift-id=123;msg-param-mass-gift-count=5;msg-param-sender-count=10;msg-param-sub-plan=1000",
+            None,
+        );
+        assert_eq!(
+            Some(ChatEvent {
+                channel: "bar".into(),
+                kind: ChatEventKind::SubMysteryGift {
+                    gifter: Some("foo".into()),
+                    count: 5,
+                    gifter_total: Some(10),
+                    tier: "1".into(),
+                }
+            }),
+            ChatEvent::from_user_notice(&mystery_gift)
+        );
+
+        let community_gift = user_notice(
+            "foo",
+            "42",
+            &format!("{};msg-param-community-gift-id=123", SUB_GIFT_TAGS),
+            None,
+        );
+        assert_eq!(None, ChatEvent::from_user_notice(&community_gift));
+
+        let gift = user_notice("foo", "42", SUB_GIFT_TAGS, None);
+        assert_eq!(
+            Some(ChatEvent {
+                channel: "bar".into(),
+                kind: ChatEventKind::SubGift {
+                    gifter: Some("foo".into()),
+                    recipient: "Baz".into(),
+                    months: 1,
+                    tier: "1".into(),
+                }
+            }),
+            ChatEvent::from_user_notice(&gift)
+        );
+    }
+
+    #[test]
+    fn test_chat_event_from_user_notice_hides_anonymous_gifters() {
+        let anonymous_gift = user_notice("ananonymousgifter", ANONYMOUS_GIFTER_ID, SUB_GIFT_TAGS, None);
+        assert_eq!(
+            Some(ChatEvent {
+                channel: "bar".into(),
+                kind: ChatEventKind::SubGift {
+                    gifter: None,
+                    recipient: "Baz".into(),
+                    months: 1,
+                    tier: "1".into(),
+                }
+            }),
+            ChatEvent::from_user_notice(&anonymous_gift)
+        );
+
+        let anonymous_mystery_gift = user_notice(
+            "ananonymousgifter",
+            ANONYMOUS_GIFTER_ID,
+            "msg-id=anonsubmysterygift;msg-param-mass-gift-count=3;msg-param-sub-plan=3000",
+            None,
+        );
+        assert_eq!(
+            Some(ChatEvent {
+                channel: "bar".into(),
+                kind: ChatEventKind::SubMysteryGift {
+                    gifter: None,
+                    count: 3,
+                    gifter_total: None,
+                    tier: "3".into(),
+                }
+            }),
+            ChatEvent::from_user_notice(&anonymous_mystery_gift)
+        );
+    }
+
+    #[test]
+    fn test_chat_event_from_user_notice_handles_announcements() {
+        let announcement = user_notice(
+            "foo",
+            "42",
+            "msg-id=announcement;msg-param-color=PRIMARY",
+            Some("Stream starts soon"),
+        );
+        assert_eq!(
+            Some(ChatEvent {
+                channel: "bar".into(),
+                kind: ChatEventKind::Announcement {
+                    user: "foo".into(),
+                    message: "Stream starts soon".into(),
+                }
+            }),
+            ChatEvent::from_user_notice(&announcement)
+        );
+
+        let unsupported = user_notice("foo", "42", "msg-id=bitsbadgetier;msg-param-threshold=1000", None);
+        assert_eq!(None, ChatEvent::from_user_notice(&unsupported));
+    }
+
+    const ANONYMOUS_GIFTER_ID: &str = "274598607";
+
+    const SUB_GIFT_TAGS: &str = "msg-id=subgift;msg-param-months=1;msg-param-gift-months=1;msg-param-recipient-display-name=Baz;msg-param-recipient-id=43;msg-param-recipient-user-name=baz;msg-param-sub-plan=1000;msg-param-sub-plan-name=Channel\\sSubscription";
+
+    fn user_notice(login: &str, user_id: &str, event_tags: &str, text: Option<&str>) -> UserNoticeMessage {
+        let text = text.map(|text| format!(" :{}", text)).unwrap_or_default();
+        let raw = format!(
+            "@badge-info=;badges=;color=;display-name={login};emotes=;flags=;id=1d7a2c36-5d2b-4b8b-8c4c-7e2e1a6b9f00;login={login};mod=0;room-id=11148817;subscriber=0;system-msg=Some\\sevent.;tmi-sent-ts=1594583782376;user-id={user_id};user-type=;{event_tags} :tmi.twitch.tv USERNOTICE #bar{text}"
+        );
+        UserNoticeMessage::try_from(IRCMessage::parse(&raw).unwrap()).unwrap()
+    }
+}
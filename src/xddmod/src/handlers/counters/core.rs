@@ -0,0 +1,97 @@
+use minijinja::value::Value;
+use minijinja::Environment;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::handlers::counters::persistence::Counter;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Mod commands to manually set or reset counters.
+///
+/// The reply pattern must capture the counter `name` and, optionally, the `value` to set it to.
+/// Without a `value` the counter is reset.
+pub struct Counters<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl Counters<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::Counters
+    }
+}
+
+impl Counters<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let captures = reply.captures(message);
+
+                    let Some(name) = captures.get("name") else {
+                        eprintln!("Missing counter name capture in reply: {:?}.", reply);
+                        return;
+                    };
+
+                    let value = match captures.get("value").map(|v| v.parse::<i64>()) {
+                        Some(Ok(value)) => value,
+                        None => 0,
+                        Some(Err(error)) => {
+                            eprintln!(
+                                "Error parsing counter value for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let counter = match Counter::set(&message.channel_login, name, value, &self.db_pool).await {
+                        Ok(counter) => counter,
+                        Err(error) => {
+                            eprintln!("Error setting counter {:?}, error: {:?}.", name, error);
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&counter)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Counter {
+    pub channel: String,
+    pub name: String,
+    pub value: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Counter {
+    pub async fn get<'a>(channel: &str, name: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    name,
+                    value,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from counters
+                where channel = $1 and name = $2
+            "#,
+            channel,
+            name,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn all_by_channel<'a>(channel: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    name,
+                    value,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from counters
+                where channel = $1
+            "#,
+            channel,
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn incr<'a>(channel: &str, name: &str, by: i64, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into counters (channel, name, value)
+                values ($1, $2, $3)
+                on conflict (channel, name) do update set
+                    value = value + excluded.value,
+                    updated_at = current_timestamp
+                returning
+                    channel,
+                    name,
+                    value,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            name,
+            by,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn set<'a>(
+        channel: &str,
+        name: &str,
+        value: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into counters (channel, name, value)
+                values ($1, $2, $3)
+                on conflict (channel, name) do update set
+                    value = excluded.value,
+                    updated_at = current_timestamp
+                returning
+                    channel,
+                    name,
+                    value,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            name,
+            value,
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
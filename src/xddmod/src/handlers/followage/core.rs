@@ -0,0 +1,118 @@
+use anyhow::anyhow;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch::channels;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::rip_bozo::core::target_login;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Follow and account creation dates of the first mentioned user or of the sender, e.g. for `!followage` and
+/// `!accountage`.
+///
+/// The dates are RFC 3339 strings, to be used with `sub_date_times` and `format_duration`, e.g.
+/// `{{ (followed_at|sub_date_times(now())).duration|format_duration }}`.
+///
+/// Requires the broadcaster token with the `moderator:read:followers` scope.
+pub struct Followage<'a> {
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl Followage<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::Followage
+    }
+}
+
+impl Followage<'_> {
+    pub async fn handle(&mut self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let login = target_login(message);
+                    let template_inputs = match self.lookup(login).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!("Error looking up followage of {:?}, error: {:?}.", login, error);
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn lookup(&mut self, login: &str) -> anyhow::Result<TemplateInputs> {
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for followage");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        let user = self
+            .helix_client
+            .get_user_from_login(login, &self.token)
+            .await?
+            .ok_or_else(|| anyhow!("No user found with login {:?}.", login))?;
+        let follower = channels::get_follower(&self.helix_client, &self.token, &self.broadcaster_id, &user.id).await?;
+
+        Ok(TemplateInputs {
+            user: user.display_name.to_string(),
+            login: user.login.to_string(),
+            created_at: user.created_at.as_str().into(),
+            followed_at: follower.map(|f| f.followed_at.as_str().into()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct TemplateInputs {
+    user: String,
+    login: String,
+    created_at: String,
+    /// Empty if the user does not follow the channel.
+    followed_at: Option<String>,
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::helix::predictions::Prediction;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::PredictionStatus;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::apis::twitch::predictions;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+pub const DEFAULT_WINDOW_SECS: i64 = 120;
+pub const OUTCOMES_SEPARATOR: char = '|';
+
+/// Mod commands to create, lock, resolve and cancel predictions.
+///
+/// The reply pattern must capture the `action` (`create`, `lock`, `resolve` or `cancel`).
+/// `create` also needs the `title` and the `|` separated `outcomes` plus, optionally, the `window` in seconds.
+/// `resolve` needs the winning `outcome`, either as its 1-based position or its title.
+///
+/// Requires the broadcaster token with the `channel:manage:predictions` scope.
+pub struct GambaCommands<'a> {
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl GambaCommands<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::GambaCommands
+    }
+}
+
+impl GambaCommands<'_> {
+    pub async fn handle(&mut self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let gamba_command = match GambaCommand::try_from(&reply.captures(message)) {
+                        Ok(gamba_command) => gamba_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing gamba command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(&gamba_command).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!("Error executing gamba command {:?}, error: {:?}.", gamba_command, error);
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn execute(&mut self, gamba_command: &GambaCommand) -> anyhow::Result<TemplateInputs> {
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for gamba commands");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        match gamba_command {
+            GambaCommand::Create {
+                title,
+                outcomes,
+                window_secs,
+            } => {
+                predictions::create_prediction(
+                    &self.helix_client,
+                    &self.token,
+                    &self.broadcaster_id,
+                    title,
+                    outcomes,
+                    *window_secs,
+                )
+                .await?;
+
+                Ok(TemplateInputs {
+                    action: gamba_command.action(),
+                    title: title.clone(),
+                    outcomes: outcomes.clone(),
+                    winner: None,
+                })
+            }
+            GambaCommand::Lock => {
+                let prediction = self.current_prediction(&[PredictionStatus::Active]).await?;
+                self.end_prediction(&prediction, PredictionStatus::Locked, None).await?;
+                Ok(TemplateInputs::new(gamba_command, &prediction, None))
+            }
+            GambaCommand::Resolve { outcome } => {
+                let prediction = self
+                    .current_prediction(&[PredictionStatus::Active, PredictionStatus::Locked])
+                    .await?;
+                let (winning_outcome_id, winner) = find_outcome(&prediction, outcome)?;
+                self.end_prediction(&prediction, PredictionStatus::Resolved, Some(winning_outcome_id))
+                    .await?;
+                Ok(TemplateInputs::new(gamba_command, &prediction, Some(winner)))
+            }
+            GambaCommand::Cancel => {
+                let prediction = self
+                    .current_prediction(&[PredictionStatus::Active, PredictionStatus::Locked])
+                    .await?;
+                self.end_prediction(&prediction, PredictionStatus::Canceled, None)
+                    .await?;
+                Ok(TemplateInputs::new(gamba_command, &prediction, None))
+            }
+        }
+    }
+
+    async fn current_prediction(&self, expected_statuses: &[PredictionStatus]) -> anyhow::Result<Prediction> {
+        let prediction = predictions::get_last_prediction(&self.helix_client, &self.token, &self.broadcaster_id)
+            .await?
+            .ok_or_else(|| anyhow!("No Predictions found for broadcaster {:?}.", self.broadcaster_id))?;
+
+        if !expected_statuses.contains(&prediction.status) {
+            bail!(
+                "Unexpected status {:?} for prediction {:?}, expected one of {:?}.",
+                prediction.status,
+                prediction,
+                expected_statuses
+            );
+        }
+
+        Ok(prediction)
+    }
+
+    async fn end_prediction(
+        &self,
+        prediction: &Prediction,
+        status: PredictionStatus,
+        winning_outcome_id: Option<String>,
+    ) -> anyhow::Result<()> {
+        predictions::end_prediction(
+            &self.helix_client,
+            &self.token,
+            &self.broadcaster_id,
+            prediction,
+            status,
+            winning_outcome_id,
+        )
+        .await
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum GambaCommand {
+    Create {
+        title: String,
+        outcomes: Vec<String>,
+        window_secs: i64,
+    },
+    Lock,
+    Resolve {
+        outcome: String,
+    },
+    Cancel,
+}
+
+impl GambaCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Create { .. } => "create",
+            Self::Lock => "lock",
+            Self::Resolve { .. } => "resolve",
+            Self::Cancel => "cancel",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for GambaCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "create" | "start" => {
+                let outcomes: Vec<String> = required_capture(captures, "outcomes")?
+                    .split(OUTCOMES_SEPARATOR)
+                    .map(str::trim)
+                    .filter(|o| !o.is_empty())
+                    .map(String::from)
+                    .collect();
+                if !(2..=10).contains(&outcomes.len()) {
+                    bail!("Expected between 2 and 10 outcomes, got {:?}.", outcomes);
+                }
+
+                let window_secs = match captures.get("window") {
+                    Some(window) => i64::from_str(window.trim())?,
+                    None => DEFAULT_WINDOW_SECS,
+                };
+                if !(30..=1800).contains(&window_secs) {
+                    bail!("Expected window between 30 and 1800 seconds, got {:?}.", window_secs);
+                }
+
+                Self::Create {
+                    title: required_capture(captures, "title")?.into(),
+                    outcomes,
+                    window_secs,
+                }
+            }
+            "lock" => Self::Lock,
+            "resolve" => Self::Resolve {
+                outcome: required_capture(captures, "outcome")?.into(),
+            },
+            "cancel" | "refund" => Self::Cancel,
+            unexpected_action => bail!("Unexpected gamba action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    title: String,
+    outcomes: Vec<String>,
+    winner: Option<String>,
+}
+
+impl TemplateInputs {
+    pub fn new(gamba_command: &GambaCommand, prediction: &Prediction, winner: Option<String>) -> Self {
+        Self {
+            action: gamba_command.action(),
+            title: prediction.title.clone(),
+            outcomes: prediction.outcomes.iter().map(|o| o.title.clone()).collect(),
+            winner,
+        }
+    }
+}
+
+/// Id and title of the outcome matching the given 1-based position or title.
+fn find_outcome(prediction: &Prediction, outcome: &str) -> anyhow::Result<(String, String)> {
+    let titles: Vec<&str> = prediction.outcomes.iter().map(|o| o.title.as_str()).collect();
+    let idx = outcome_idx(&titles, outcome)
+        .ok_or_else(|| anyhow!("No outcome matching {:?} in prediction {:?}.", outcome, prediction))?;
+
+    let outcome = &prediction.outcomes[idx];
+    Ok((outcome.id.to_string(), outcome.title.clone()))
+}
+
+fn outcome_idx(titles: &[&str], outcome: &str) -> Option<usize> {
+    match outcome.parse::<usize>() {
+        Ok(position) if (1..=titles.len()).contains(&position) => Some(position - 1),
+        _ => titles.iter().position(|t| t.eq_ignore_ascii_case(outcome)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_gamba_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            GambaCommand::Create {
+                title: "Win?".into(),
+                outcomes: vec!["Yes".into(), "No".into()],
+                window_secs: 300,
+            },
+            GambaCommand::try_from(&test_captures(&[
+                ("action", "create"),
+                ("title", "Win?"),
+                ("outcomes", "Yes | No |"),
+                ("window", "300")
+            ]))
+            .unwrap()
+        );
+        assert_eq!(
+            GambaCommand::Create {
+                title: "Win?".into(),
+                outcomes: vec!["Yes".into(), "No".into()],
+                window_secs: DEFAULT_WINDOW_SECS,
+            },
+            GambaCommand::try_from(&test_captures(&[
+                ("action", "START"),
+                ("title", "Win?"),
+                ("outcomes", "Yes|No")
+            ]))
+            .unwrap()
+        );
+        assert!(GambaCommand::try_from(&test_captures(&[
+            ("action", "create"),
+            ("title", "Win?"),
+            ("outcomes", "Yes")
+        ]))
+        .is_err());
+        assert!(GambaCommand::try_from(&test_captures(&[
+            ("action", "create"),
+            ("title", "Win?"),
+            ("outcomes", "Yes|No"),
+            ("window", "10")
+        ]))
+        .is_err());
+        assert_eq!(
+            GambaCommand::Lock,
+            GambaCommand::try_from(&test_captures(&[("action", "lock")])).unwrap()
+        );
+        assert_eq!(
+            GambaCommand::Resolve { outcome: "2".into() },
+            GambaCommand::try_from(&test_captures(&[("action", "resolve"), ("outcome", "2")])).unwrap()
+        );
+        assert!(GambaCommand::try_from(&test_captures(&[("action", "resolve")])).is_err());
+        assert_eq!(
+            GambaCommand::Cancel,
+            GambaCommand::try_from(&test_captures(&[("action", "cancel")])).unwrap()
+        );
+        assert!(GambaCommand::try_from(&test_captures(&[("action", "foo")])).is_err());
+    }
+
+    #[test]
+    fn test_outcome_idx_works_as_expected() {
+        let titles = ["Yes", "No"];
+
+        assert_eq!(Some(0), outcome_idx(&titles, "1"));
+        assert_eq!(Some(1), outcome_idx(&titles, "2"));
+        assert_eq!(None, outcome_idx(&titles, "3"));
+        assert_eq!(Some(1), outcome_idx(&titles, "no"));
+        assert_eq!(None, outcome_idx(&titles, "maybe"));
+    }
+}
@@ -0,0 +1,253 @@
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::handlers::gamba_time::persistence::GambaRecord;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Statistics of the persisted predictions of the channel, e.g. for `!gambastats`.
+///
+/// Believers are the ones betting on the first outcome, doubters the ones betting on the second.
+pub struct GambaStats<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl GambaStats<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::GambaStats
+    }
+}
+
+impl GambaStats<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let gamba_records = match GambaRecord::all_by_channel(&message.channel_login, &self.db_pool).await {
+                        Ok(gamba_records) => gamba_records,
+                        Err(error) => {
+                            eprintln!(
+                                "Error loading gambas of channel {:?}, error: {:?}.",
+                                message.channel_login, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let channel_gamba_stats = ChannelGambaStats::from(gamba_records.as_slice());
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&channel_gamba_stats)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChannelGambaStats {
+    pub total: u64,
+    pub paid: u64,
+    pub refunded: u64,
+    pub believers_wins: u64,
+    pub doubters_wins: u64,
+    /// Percentage of paid gambas won by the believers.
+    pub believers_win_rate: Option<f64>,
+    /// Of the paid gambas only.
+    pub biggest_pots: Vec<Pot>,
+    pub current_streak: Option<Streak>,
+    pub longest_believers_streak: u64,
+    pub longest_doubters_streak: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Pot {
+    pub title: String,
+    pub winner: Option<String>,
+    pub total_users: i64,
+    pub total_betted_channel_points: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Streak {
+    pub winners: Winners,
+    pub len: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Winners {
+    Believers,
+    Doubters,
+}
+
+pub const BIGGEST_POTS: usize = 3;
+
+impl From<&[GambaRecord]> for ChannelGambaStats {
+    /// Expects the gambas ordered from the oldest to the newest.
+    fn from(gamba_records: &[GambaRecord]) -> Self {
+        let mut stats = Self {
+            total: gamba_records.len() as u64,
+            ..Default::default()
+        };
+
+        for gamba_record in gamba_records {
+            match gamba_record.state.as_str() {
+                "Paid" => stats.paid += 1,
+                "Refunded" => stats.refunded += 1,
+                _ => {}
+            }
+
+            let winners = match (gamba_record.state.as_str(), gamba_record.winner_idx) {
+                ("Paid", Some(0)) => Winners::Believers,
+                ("Paid", Some(_)) => Winners::Doubters,
+                _ => continue,
+            };
+
+            match winners {
+                Winners::Believers => stats.believers_wins += 1,
+                Winners::Doubters => stats.doubters_wins += 1,
+            }
+
+            let current_streak = match stats.current_streak.take() {
+                Some(streak) if streak.winners == winners => Streak {
+                    winners,
+                    len: streak.len + 1,
+                },
+                _ => Streak { winners, len: 1 },
+            };
+            match winners {
+                Winners::Believers => {
+                    stats.longest_believers_streak = stats.longest_believers_streak.max(current_streak.len)
+                }
+                Winners::Doubters => {
+                    stats.longest_doubters_streak = stats.longest_doubters_streak.max(current_streak.len)
+                }
+            }
+            stats.current_streak = Some(current_streak);
+        }
+
+        let decided = stats.believers_wins + stats.doubters_wins;
+        stats.believers_win_rate = (decided > 0).then(|| stats.believers_wins as f64 * 100.0 / decided as f64);
+
+        let mut pots: Vec<&GambaRecord> = gamba_records.iter().filter(|g| g.state == "Paid").collect();
+        pots.sort_by_key(|g| std::cmp::Reverse(g.total_betted_channel_points));
+        stats.biggest_pots = pots
+            .into_iter()
+            .take(BIGGEST_POTS)
+            .map(|g| Pot {
+                title: g.title.clone(),
+                winner: g.winner.clone(),
+                total_users: g.total_users,
+                total_betted_channel_points: g.total_betted_channel_points,
+            })
+            .collect();
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::Fake;
+    use fake::Faker;
+    use sqlx::types::Json;
+
+    use super::*;
+
+    #[test]
+    fn test_channel_gamba_stats_from_gamba_records_works_as_expected() {
+        let gamba_records = vec![
+            dummy_gamba_record("Paid", Some(0), 100),
+            dummy_gamba_record("Paid", Some(0), 500),
+            dummy_gamba_record("Refunded", None, 1000),
+            dummy_gamba_record("Paid", Some(1), 300),
+            dummy_gamba_record("Paid", Some(1), 50),
+            dummy_gamba_record("Paid", Some(1), 10),
+            dummy_gamba_record("Up", None, 200),
+        ];
+
+        let stats = ChannelGambaStats::from(gamba_records.as_slice());
+
+        assert_eq!(7, stats.total);
+        assert_eq!(5, stats.paid);
+        assert_eq!(1, stats.refunded);
+        assert_eq!(2, stats.believers_wins);
+        assert_eq!(3, stats.doubters_wins);
+        assert_eq!(Some(40.0), stats.believers_win_rate);
+        assert_eq!(
+            vec![500, 300, 100],
+            stats
+                .biggest_pots
+                .iter()
+                .map(|p| p.total_betted_channel_points)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(Streak {
+                winners: Winners::Doubters,
+                len: 3
+            }),
+            stats.current_streak
+        );
+        assert_eq!(2, stats.longest_believers_streak);
+        assert_eq!(3, stats.longest_doubters_streak);
+    }
+
+    #[test]
+    fn test_channel_gamba_stats_from_no_gamba_records_works_as_expected() {
+        assert_eq!(ChannelGambaStats::default(), ChannelGambaStats::from([].as_slice()));
+    }
+
+    fn dummy_gamba_record(state: &str, winner_idx: Option<i64>, total_betted_channel_points: i64) -> GambaRecord {
+        GambaRecord {
+            id: Faker.fake(),
+            channel: Faker.fake(),
+            title: Faker.fake(),
+            sides: Json(vec![]),
+            state: state.into(),
+            winner: winner_idx.map(|_| Faker.fake()),
+            winner_idx,
+            total_users: Faker.fake(),
+            total_betted_channel_points,
+            started_at: Faker.fake(),
+            ended_at: None,
+            created_at: Faker.fake(),
+            updated_at: Faker.fake(),
+        }
+    }
+}
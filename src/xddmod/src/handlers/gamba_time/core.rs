@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use anyhow::anyhow;
@@ -8,31 +10,74 @@ use chrono::Utc;
 use fake::Dummy;
 use fake::Fake;
 use fake::Faker;
+use minijinja::value::Value;
 use minijinja::Environment;
 use serde::Deserialize;
 use serde::Serialize;
 use sqlx::SqlitePool;
 use twitch_api::helix::predictions::get_predictions::GetPredictionsRequest;
 use twitch_api::helix::predictions::Prediction;
+use twitch_api::twitch_oauth2::TwitchToken;
 use twitch_api::twitch_oauth2::UserToken;
 use twitch_api::types::PredictionOutcome;
 use twitch_api::types::PredictionStatus;
+use twitch_api::types::PredictionTopPredictors;
+use twitch_api::types::Timestamp;
 use twitch_api::types::UserId;
 use twitch_api::HelixClient;
 use twitch_irc::message::PrivmsgMessage;
 use twitch_irc::message::ServerMessage;
 
-use crate::auth::IRCClient;
+use crate::apis::twitch::eventsub::Event;
+use crate::apis::twitch::eventsub::PredictionEvent;
+use crate::apis::twitch::eventsub::PredictionEventOutcome;
+use crate::apis::twitch::eventsub::TopPredictor;
+use crate::handlers::gamba_time::persistence::GambaRecord;
+use crate::handlers::persistence::Channel;
 use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::MatchableMessage;
 use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
 
+/// Renders the current prediction on chat commands and announces its begin, lock and end.
+///
+/// Announcements are driven by EventSub prediction events and matched against `prediction.begin`,
+/// `prediction.lock` and `prediction.end`. The latest prediction received by EventSub is cached, so chat
+/// commands hit Helix only when nothing has been received yet.
+/// Every observed prediction is persisted as a [`GambaRecord`].
 pub struct GambaTime<'a> {
-    pub token: UserToken,
     pub broadcaster_id: UserId,
     pub helix_client: HelixClient<'a, reqwest::Client>,
-    pub irc_client: IRCClient,
+    pub outbox: Outbox,
+    pub streams: Streams,
     pub db_pool: SqlitePool,
     pub templates_env: Environment<'a>,
+    token: tokio::sync::Mutex<UserToken>,
+    last_gambas: Mutex<HashMap<String, Gamba>>,
+}
+
+impl<'a> GambaTime<'a> {
+    pub fn new(
+        token: UserToken,
+        broadcaster_id: UserId,
+        helix_client: HelixClient<'a, reqwest::Client>,
+        outbox: Outbox,
+        streams: Streams,
+        db_pool: SqlitePool,
+        templates_env: Environment<'a>,
+    ) -> Self {
+        Self {
+            broadcaster_id,
+            helix_client,
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            token: tokio::sync::Mutex::new(token),
+            last_gambas: Mutex::new(HashMap::new()),
+        }
+    }
 }
 
 impl GambaTime<'_> {
@@ -42,44 +87,32 @@ impl GambaTime<'_> {
 }
 
 impl GambaTime<'_> {
-    pub async fn handle(&self, server_message: &ServerMessage) {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
         if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
-            match Reply::matching(self.handler(), message, &self.db_pool).await.as_slice() {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
                 [reply] => {
-                    let prediction_request = GetPredictionsRequest::builder()
-                        .broadcaster_id(self.broadcaster_id.clone())
-                        .first(Some(1))
-                        .build();
-
-                    let predictions: Vec<Prediction> = self
-                        .helix_client
-                        .req_get(prediction_request.clone(), &self.token)
+                    let Some(gamba) = self.current_gamba(&message.channel_login).await else {
+                        return;
+                    };
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&gamba)),
+                        )
                         .await
-                        .unwrap()
-                        .data;
-
-                    match predictions.first() {
-                        Some(prediction) => match Gamba::try_from(prediction.clone()) {
-                            Ok(gamba) => {
-                                match reply.render_template(
-                                    &self.templates_env,
-                                    Some(&minijinja::value::Value::from_serialize(&gamba)),
-                                ) {
-                                    Ok(rendered_reply) if rendered_reply.is_empty() => {
-                                        eprintln!("Rendered reply template empty: {:?}.", reply)
-                                    }
-                                    Ok(rendered_reply) => {
-                                        self.irc_client.say_in_reply_to(message, rendered_reply).await.unwrap()
-                                    }
-                                    Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
-                                }
-                            }
-                            Err(e) => eprintln!(
-                                "Error building GambaData for Prediction {:?}, error: {:?}.",
-                                prediction, e
-                            ),
-                        },
-                        None => eprintln!("No Predictions found for request {:?}.", prediction_request),
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
                     }
                 }
                 [] => {}
@@ -92,38 +125,257 @@ impl GambaTime<'_> {
     }
 }
 
+impl GambaTime<'_> {
+    pub async fn handle_event(&self, event: &Event) {
+        let (prediction, gamba_event_kind) = match event {
+            Event::PredictionBegin(prediction) => (prediction, Some("prediction.begin")),
+            Event::PredictionProgress(prediction) => (prediction, None),
+            Event::PredictionLock(prediction) => (prediction, Some("prediction.lock")),
+            Event::PredictionEnd(prediction) => (prediction, Some("prediction.end")),
+            _ => return,
+        };
+
+        let gamba = match Gamba::try_from(event) {
+            Ok(gamba) => gamba,
+            Err(e) => {
+                eprintln!("Error building Gamba for event {:?}, error: {:?}.", event, e);
+                return;
+            }
+        };
+
+        let gamba = match self.last_gambas.lock() {
+            Ok(mut last_gambas) => {
+                let gamba = gamba.with_duration_of(last_gambas.get(&prediction.broadcaster_user_login));
+                last_gambas.insert(prediction.broadcaster_user_login.clone(), gamba.clone());
+                gamba
+            }
+            Err(error) => {
+                eprintln!("Cannot get last gambas Lock, error: {:?}", error);
+                gamba
+            }
+        };
+
+        if let Err(error) = GambaRecord::upsert(&prediction.broadcaster_user_login, &gamba, &self.db_pool).await {
+            eprintln!("Error persisting gamba {:?}, error: {:?}.", gamba, error);
+        }
+
+        let Some(gamba_event_kind) = gamba_event_kind else {
+            return;
+        };
+        let gamba_event = GambaEvent {
+            channel: prediction.broadcaster_user_login.clone(),
+            kind: gamba_event_kind,
+        };
+
+        let channel = Channel::by_name_or_default(&gamba_event.channel, &self.db_pool).await;
+        let is_live = self.streams.is_live(&gamba_event.channel);
+        match Reply::matching(self.handler(), &gamba_event, &channel, is_live, &self.db_pool)
+            .await
+            .as_slice()
+        {
+            [reply] => match reply
+                .render_template(
+                    &self.templates_env,
+                    &channel,
+                    &self.streams,
+                    &self.db_pool,
+                    Some(&Value::from_serialize(&gamba)),
+                )
+                .await
+            {
+                Ok(rendered_reply) if rendered_reply.is_empty() => {
+                    eprintln!("Rendered reply template empty: {:?}.", reply)
+                }
+                Ok(rendered_reply) => self.outbox.say(&gamba_event.channel, rendered_reply),
+                Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+            },
+            [] => {}
+            multiple_matching_replies => eprintln!(
+                "Multiple matching replies for gamba event: {:?}, {:?}.",
+                multiple_matching_replies, gamba_event
+            ),
+        }
+    }
+
+    async fn current_gamba(&self, channel: &str) -> Option<Gamba> {
+        let last_gamba = match self.last_gambas.lock() {
+            Ok(last_gambas) => last_gambas.get(channel).cloned(),
+            Err(error) => {
+                eprintln!("Cannot get last gambas Lock, error: {:?}", error);
+                None
+            }
+        };
+        if last_gamba.is_some() {
+            return last_gamba;
+        }
+
+        let prediction_request = GetPredictionsRequest::builder()
+            .broadcaster_id(self.broadcaster_id.clone())
+            .first(Some(1))
+            .build();
+
+        let mut token = self.token.lock().await;
+        if token.is_elapsed() {
+            eprintln!("Refreshing token for gamba time");
+            if let Err(error) = token.refresh_token(self.helix_client.get_client()).await {
+                eprintln!("Error refreshing token for gamba time, error: {:?}.", error);
+                return None;
+            }
+        }
+
+        let predictions: Vec<Prediction> = match self.helix_client.req_get(prediction_request.clone(), &*token).await {
+            Ok(response) => response.data,
+            Err(error) => {
+                eprintln!(
+                    "Error getting Predictions for request {:?}, error: {:?}.",
+                    prediction_request, error
+                );
+                return None;
+            }
+        };
+        drop(token);
+
+        match predictions.first() {
+            Some(prediction) => match Gamba::try_from(prediction.clone()) {
+                Ok(gamba) => {
+                    if let Err(error) = GambaRecord::upsert(channel, &gamba, &self.db_pool).await {
+                        eprintln!("Error persisting gamba {:?}, error: {:?}.", gamba, error);
+                    }
+                    Some(gamba)
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Error building GambaData for Prediction {:?}, error: {:?}.",
+                        prediction, e
+                    );
+                    None
+                }
+            },
+            None => {
+                eprintln!("No Predictions found for request {:?}.", prediction_request);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct GambaEvent {
+    channel: String,
+    kind: &'static str,
+}
+
+impl MatchableMessage for GambaEvent {
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn text(&self) -> &str {
+        self.kind
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Gamba {
+    pub id: String,
     pub title: String,
     pub sides: Vec<Side>,
     pub state: GambaState,
     pub duration: Duration,
     pub started_at: DateTime<Utc>,
+    pub total_users: i64,
+    pub total_betted_channel_points: i64,
 }
 
-impl Dummy<Faker> for Gamba {
-    fn dummy_with_rng<R: rand::Rng + ?Sized>(_: &Faker, rng: &mut R) -> Self {
+impl Gamba {
+    pub fn new(
+        id: String,
+        title: String,
+        sides: Vec<Side>,
+        state: GambaState,
+        duration: Duration,
+        started_at: DateTime<Utc>,
+    ) -> Self {
         Self {
-            title: Faker.fake_with_rng(rng),
-            sides: Faker.fake_with_rng(rng),
-            state: Faker.fake_with_rng(rng),
-            duration: std::time::Duration::new(Faker.fake_with_rng(rng), Faker.fake_with_rng(rng)),
-            started_at: Faker.fake_with_rng(rng),
+            total_users: sides.iter().filter_map(|s| s.users).sum(),
+            total_betted_channel_points: sides.iter().filter_map(|s| s.betted_channel_points).sum(),
+            id,
+            title,
+            sides,
+            state,
+            duration,
+            started_at,
+        }
+    }
+
+    /// Prediction end events don't carry `locks_at`, hence the duration of the same gamba previously received is kept.
+    fn with_duration_of(self, last_gamba: Option<&Gamba>) -> Self {
+        match last_gamba {
+            Some(last_gamba) if last_gamba.id == self.id && self.duration.is_zero() => Self {
+                duration: last_gamba.duration,
+                ..self
+            },
+            _ => self,
         }
     }
 }
 
+impl Dummy<Faker> for Gamba {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(_: &Faker, rng: &mut R) -> Self {
+        Self::new(
+            Faker.fake_with_rng(rng),
+            Faker.fake_with_rng(rng),
+            Faker.fake_with_rng(rng),
+            Faker.fake_with_rng(rng),
+            std::time::Duration::new(Faker.fake_with_rng(rng), Faker.fake_with_rng(rng)),
+            Faker.fake_with_rng(rng),
+        )
+    }
+}
+
 impl TryFrom<Prediction> for Gamba {
     type Error = anyhow::Error;
 
     fn try_from(x: Prediction) -> Result<Self, Self::Error> {
-        Ok(Self {
-            title: x.title.clone(),
-            sides: x.outcomes.clone().into_iter().map(Side::from).collect(),
-            state: GambaState::try_from(x.clone())?,
-            duration: Duration::from_secs(x.prediction_window as u64),
-            started_at: Utc.timestamp_nanos(x.created_at.to_utc().unix_timestamp()),
-        })
+        Ok(Self::new(
+            x.id.to_string(),
+            x.title.clone(),
+            x.outcomes.clone().into_iter().map(Side::from).collect(),
+            GambaState::try_from(x.clone())?,
+            Duration::from_secs(x.prediction_window as u64),
+            to_date_time(&x.created_at).ok_or_else(|| anyhow!("Invalid created_at in prediction {:?}.", x))?,
+        ))
+    }
+}
+
+impl TryFrom<&Event> for Gamba {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let (x, state) = match event {
+            Event::PredictionBegin(x) | Event::PredictionProgress(x) => (x, GambaState::Up),
+            Event::PredictionLock(x) => (
+                x,
+                GambaState::Closed {
+                    closed_at: x
+                        .locked_at
+                        .ok_or_else(|| anyhow!("Missing locked_at in locked prediction {:?}.", x))?,
+                },
+            ),
+            Event::PredictionEnd(x) => (x, GambaState::try_from(x)?),
+            unexpected_event => bail!("Unexpected event {:?} for Gamba.", unexpected_event),
+        };
+
+        Ok(Self::new(
+            x.id.clone(),
+            x.title.clone(),
+            x.outcomes.clone().into_iter().map(Side::from).collect(),
+            state,
+            x.locks_at
+                .and_then(|locks_at| (locks_at - x.started_at).to_std().ok())
+                .unwrap_or_default(),
+            x.started_at,
+        ))
     }
 }
 
@@ -133,6 +385,17 @@ pub struct Side {
     pub users: Option<i64>,
     pub betted_channel_points: Option<i64>,
     pub color: String,
+    pub top_predictors: Vec<Predictor>,
+}
+
+impl Side {
+    pub fn biggest_winner(&self) -> Option<Predictor> {
+        self.top_predictors
+            .iter()
+            .filter(|p| p.won_channel_points.is_some_and(|won| won > 0))
+            .max_by_key(|p| p.won_channel_points)
+            .cloned()
+    }
 }
 
 impl From<PredictionOutcome> for Side {
@@ -142,6 +405,56 @@ impl From<PredictionOutcome> for Side {
             users: x.users,
             betted_channel_points: x.channel_points,
             color: x.color,
+            top_predictors: x
+                .top_predictors
+                .unwrap_or_default()
+                .into_iter()
+                .map(Predictor::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<PredictionEventOutcome> for Side {
+    fn from(x: PredictionEventOutcome) -> Self {
+        Self {
+            title: x.title,
+            users: x.users,
+            betted_channel_points: x.channel_points,
+            color: x.color,
+            top_predictors: x
+                .top_predictors
+                .unwrap_or_default()
+                .into_iter()
+                .map(Predictor::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Dummy)]
+pub struct Predictor {
+    pub name: String,
+    pub used_channel_points: i64,
+    pub won_channel_points: Option<i64>,
+}
+
+impl From<PredictionTopPredictors> for Predictor {
+    fn from(x: PredictionTopPredictors) -> Self {
+        Self {
+            name: x.user_name.to_string(),
+            used_channel_points: x.channel_points_used,
+            won_channel_points: x.channel_points_won,
+        }
+    }
+}
+
+impl From<TopPredictor> for Predictor {
+    fn from(x: TopPredictor) -> Self {
+        Self {
+            name: x.user_name,
+            used_channel_points: x.channel_points_used,
+            won_channel_points: x.channel_points_won,
         }
     }
 }
@@ -150,9 +463,36 @@ impl From<PredictionOutcome> for Side {
 #[serde(tag = "name")]
 pub enum GambaState {
     Up,
-    Closed { closed_at: DateTime<Utc> },
-    Paid { winner: Side, paid_at: DateTime<Utc> },
-    Refunded { refunded_at: DateTime<Utc> },
+    Closed {
+        closed_at: DateTime<Utc>,
+    },
+    Paid {
+        winner: Side,
+        biggest_winner: Option<Predictor>,
+        paid_at: DateTime<Utc>,
+    },
+    Refunded {
+        refunded_at: DateTime<Utc>,
+    },
+}
+
+impl GambaState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Up => "Up",
+            Self::Closed { .. } => "Closed",
+            Self::Paid { .. } => "Paid",
+            Self::Refunded { .. } => "Refunded",
+        }
+    }
+
+    pub fn ended_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Up | Self::Closed { .. } => None,
+            Self::Paid { paid_at, .. } => Some(*paid_at),
+            Self::Refunded { refunded_at } => Some(*refunded_at),
+        }
+    }
 }
 
 impl TryFrom<Prediction> for GambaState {
@@ -165,7 +505,7 @@ impl TryFrom<Prediction> for GambaState {
                 closed_at: x
                     .clone()
                     .ended_at
-                    .map(|t| Utc.timestamp_nanos(t.to_utc().unix_timestamp()))
+                    .and_then(|t| to_date_time(&t))
                     .ok_or_else(|| anyhow!("Missing ended_at in {:?} prediction {:?}.", x.status, x))?,
             },
             PredictionStatus::Resolved => {
@@ -191,11 +531,12 @@ impl TryFrom<Prediction> for GambaState {
                     })?;
 
                 Self::Paid {
+                    biggest_winner: winner.biggest_winner(),
                     winner,
                     paid_at: x
                         .clone()
                         .locked_at
-                        .map(|t| Utc.timestamp_nanos(t.to_utc().unix_timestamp()))
+                        .and_then(|t| to_date_time(&t))
                         .ok_or_else(|| anyhow!("Missing closed_at in {:?} prediction {:?}.", x.status, x))?,
                 }
             }
@@ -203,10 +544,162 @@ impl TryFrom<Prediction> for GambaState {
                 refunded_at: x
                     .clone()
                     .locked_at
-                    .map(|t| Utc.timestamp_nanos(t.to_utc().unix_timestamp()))
+                    .and_then(|t| to_date_time(&t))
                     .ok_or_else(|| anyhow!("Missing locked_at in {:?} prediction {:?}.", x.status, x))?,
             },
             unexpected_variant => bail!("Unexpected variant {:?} for prediction {:?}.", unexpected_variant, x),
         })
     }
 }
+
+impl TryFrom<&PredictionEvent> for GambaState {
+    type Error = anyhow::Error;
+
+    fn try_from(x: &PredictionEvent) -> Result<Self, Self::Error> {
+        let ended_at = x
+            .ended_at
+            .ok_or_else(|| anyhow!("Missing ended_at in ended prediction {:?}.", x))?;
+
+        Ok(match x.status.as_deref() {
+            Some("resolved") => {
+                let winner_id = x
+                    .winning_outcome_id
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Missing winning_outcome_id in resolved prediction {:?}.", x))?;
+
+                let winner = x
+                    .outcomes
+                    .iter()
+                    .find(|o| &o.id == winner_id)
+                    .cloned()
+                    .map(Side::from)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Missing outcome matching winner_id {:?} in resolved prediction {:?}.",
+                            winner_id,
+                            x
+                        )
+                    })?;
+
+                Self::Paid {
+                    biggest_winner: winner.biggest_winner(),
+                    winner,
+                    paid_at: ended_at,
+                }
+            }
+            Some("canceled") => Self::Refunded { refunded_at: ended_at },
+            unexpected_status => bail!("Unexpected status {:?} for prediction {:?}.", unexpected_status, x),
+        })
+    }
+}
+
+fn to_date_time(timestamp: &Timestamp) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(timestamp.to_utc().unix_timestamp(), 0).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_gamba_try_from_prediction_end_event_works_as_expected() {
+        let event = Event::parse(
+            "channel.prediction.end",
+            json!({
+                "id": "1243456",
+                "broadcaster_user_id": "1337",
+                "broadcaster_user_login": "cool_user",
+                "broadcaster_user_name": "Cool_User",
+                "title": "Aren’t shoes just really hard socks?",
+                "winning_outcome_id": "12345",
+                "outcomes": [
+                    {
+                        "id": "12345",
+                        "title": "Yeah!",
+                        "color": "blue",
+                        "users": 2,
+                        "channel_points": 15000,
+                        "top_predictors": [
+                            {
+                                "user_name": "Cool_User",
+                                "user_login": "cool_user",
+                                "user_id": "1234",
+                                "channel_points_won": 10000,
+                                "channel_points_used": 500
+                            },
+                            {
+                                "user_name": "Coolest_User",
+                                "user_login": "coolest_user",
+                                "user_id": "1236",
+                                "channel_points_won": 20000,
+                                "channel_points_used": 14500
+                            }
+                        ]
+                    },
+                    {
+                        "id": "22435",
+                        "title": "No!",
+                        "users": 2,
+                        "channel_points": 200,
+                        "color": "pink",
+                        "top_predictors": []
+                    }
+                ],
+                "status": "resolved",
+                "started_at": "2020-07-15T17:16:03.17106713Z",
+                "ended_at": "2020-07-15T17:16:11.17106713Z"
+            }),
+        )
+        .unwrap();
+
+        let gamba = Gamba::try_from(&event).unwrap();
+
+        assert_eq!(4, gamba.total_users);
+        assert_eq!(15200, gamba.total_betted_channel_points);
+        let begin_gamba = Gamba {
+            duration: Duration::from_secs(120),
+            ..gamba.clone()
+        };
+        let gamba = gamba.with_duration_of(Some(&begin_gamba));
+        assert_eq!(Duration::from_secs(120), gamba.duration);
+        match gamba.state {
+            GambaState::Paid {
+                winner, biggest_winner, ..
+            } => {
+                assert_eq!("Yeah!", winner.title);
+                assert_eq!(
+                    Some(Predictor {
+                        name: "Coolest_User".into(),
+                        used_channel_points: 14500,
+                        won_channel_points: Some(20000),
+                    }),
+                    biggest_winner
+                );
+            }
+            unexpected_state => panic!("Unexpected GambaState {:?}", unexpected_state),
+        }
+    }
+
+    #[test]
+    fn test_to_date_time_works_as_expected() {
+        let timestamp: Timestamp = serde_json::from_value(json!("2020-07-15T17:16:03Z")).unwrap();
+
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2020, 7, 15, 17, 16, 3).unwrap()),
+            to_date_time(&timestamp)
+        );
+    }
+
+    #[test]
+    fn test_gamba_try_from_non_prediction_event_fails() {
+        let event = Event::parse(
+            "stream.offline",
+            json!({"broadcaster_user_id": "1", "broadcaster_user_login": "foo", "broadcaster_user_name": "Foo"}),
+        )
+        .unwrap();
+
+        assert!(Gamba::try_from(&event).is_err());
+    }
+}
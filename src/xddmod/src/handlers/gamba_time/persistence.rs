@@ -0,0 +1,132 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+use sqlx::types::Json;
+
+use crate::handlers::gamba_time::core::Gamba;
+use crate::handlers::gamba_time::core::GambaState;
+use crate::handlers::gamba_time::core::Side;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GambaRecord {
+    pub id: String,
+    pub channel: String,
+    pub title: String,
+    pub sides: Json<Vec<Side>>,
+    pub state: String,
+    pub winner: Option<String>,
+    pub winner_idx: Option<i64>,
+    pub total_users: i64,
+    pub total_betted_channel_points: i64,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GambaRecord {
+    pub async fn upsert<'a>(channel: &str, gamba: &Gamba, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Self> {
+        let sides = Json(gamba.sides.clone());
+        let state = gamba.state.name();
+        let (winner, winner_idx) = match &gamba.state {
+            GambaState::Paid { winner, .. } => (
+                Some(winner.title.clone()),
+                gamba
+                    .sides
+                    .iter()
+                    .position(|s| s.title == winner.title)
+                    .map(|idx| idx as i64),
+            ),
+            _ => (None, None),
+        };
+        let ended_at = gamba.state.ended_at();
+
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into gambas (
+                    id,
+                    channel,
+                    title,
+                    sides,
+                    state,
+                    winner,
+                    winner_idx,
+                    total_users,
+                    total_betted_channel_points,
+                    started_at,
+                    ended_at
+                )
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                on conflict (id) do update set
+                    title = excluded.title,
+                    sides = excluded.sides,
+                    state = excluded.state,
+                    winner = excluded.winner,
+                    winner_idx = excluded.winner_idx,
+                    total_users = excluded.total_users,
+                    total_betted_channel_points = excluded.total_betted_channel_points,
+                    ended_at = excluded.ended_at,
+                    updated_at = current_timestamp
+                returning
+                    id as "id!",
+                    channel,
+                    title,
+                    sides as "sides!: Json<Vec<Side>>",
+                    state,
+                    winner,
+                    winner_idx,
+                    total_users,
+                    total_betted_channel_points,
+                    started_at as "started_at!: DateTime<Utc>",
+                    ended_at as "ended_at: DateTime<Utc>",
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            gamba.id,
+            channel,
+            gamba.title,
+            sides,
+            state,
+            winner,
+            winner_idx,
+            gamba.total_users,
+            gamba.total_betted_channel_points,
+            gamba.started_at,
+            ended_at,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Gambas of the channel, oldest first.
+    pub async fn all_by_channel<'a>(channel: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    id as "id!",
+                    channel,
+                    title,
+                    sides as "sides!: Json<Vec<Side>>",
+                    state,
+                    winner,
+                    winner_idx,
+                    total_users,
+                    total_betted_channel_points,
+                    started_at as "started_at!: DateTime<Utc>",
+                    ended_at as "ended_at: DateTime<Utc>",
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from gambas
+                where channel = $1
+                order by started_at asc
+            "#,
+            channel,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -4,13 +4,16 @@ use sqlx::SqlitePool;
 use twitch_irc::message::PrivmsgMessage;
 use twitch_irc::message::ServerMessage;
 
-use crate::auth::IRCClient;
+use crate::handlers::persistence::Channel;
 use crate::handlers::persistence::Handler;
 use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
 use crate::poor_man_throttling;
+use crate::streams::Streams;
 
 pub struct Npc<'a> {
-    pub irc_client: IRCClient,
+    pub outbox: Outbox,
+    pub streams: Streams,
     pub db_pool: SqlitePool,
     pub templates_env: Environment<'a>,
 }
@@ -22,9 +25,13 @@ impl Npc<'_> {
 }
 
 impl Npc<'_> {
-    pub async fn handle(&self, server_message: &ServerMessage) {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
         if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
-            match Reply::matching(self.handler(), message, &self.db_pool).await.as_slice() {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
                 [reply] => {
                     // FIXME: poor man throttling
                     match poor_man_throttling::should_throttle(message, reply) {
@@ -41,11 +48,14 @@ impl Npc<'_> {
                         }
                     }
 
-                    match reply.render_template::<Value>(&self.templates_env, None) {
+                    match reply
+                        .render_template::<Value>(&self.templates_env, channel, &self.streams, &self.db_pool, None)
+                        .await
+                    {
                         Ok(rendered_reply) if rendered_reply.is_empty() => {
                             eprintln!("Rendered reply template empty: {:?}", reply)
                         }
-                        Ok(rendered_reply) => self.irc_client.say_in_reply_to(message, rendered_reply).await.unwrap(),
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
                         Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
                     }
                 }
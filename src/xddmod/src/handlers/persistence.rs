@@ -1,14 +1,24 @@
-use minijinja::context;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use chrono_tz::Tz;
 use minijinja::Environment;
 use regex::RegexBuilder;
 use serde::Deserialize;
 use serde::Serialize;
 use sqlx::sqlite::SqliteExecutor;
 use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::NaiveDate;
+use sqlx::types::chrono::NaiveTime;
 use sqlx::types::chrono::Utc;
 use sqlx::types::Json;
+use sqlx::SqlitePool;
 use twitch_irc::message::PrivmsgMessage;
 
+use crate::streams::Streams;
+use crate::templates_env;
+
 #[derive(Debug, Clone)]
 pub struct Reply {
     pub id: i64,
@@ -19,6 +29,11 @@ pub struct Reply {
     pub channel: Option<String>,
     pub enabled: bool,
     pub additional_inputs: Option<Json<serde_json::Value>>,
+    pub active_from: Option<DateTime<Utc>>,
+    pub active_until: Option<DateTime<Utc>>,
+    pub daily_active_from: Option<NaiveTime>,
+    pub daily_active_until: Option<NaiveTime>,
+    pub stream_condition: Option<StreamCondition>,
     pub created_by: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -50,14 +65,19 @@ impl Reply {
     pub async fn matching<'a>(
         handler: Handler,
         matchable_message: &impl MatchableMessage,
+        channel: &Channel,
+        is_live: Option<bool>,
         executor: impl SqliteExecutor<'a>,
     ) -> Vec<Reply> {
         let matchable_message_text = matchable_message.text();
+        let now = Utc::now();
+        let timezone = channel.tz();
 
         Self::all(handler, matchable_message.channel(), executor)
             .await
             .unwrap()
             .into_iter()
+            .filter(|reply| reply.is_active(now, timezone, is_live))
             .filter(|reply| {
                 match RegexBuilder::new(&reply.pattern)
                     .case_insensitive(reply.case_insensitive)
@@ -73,16 +93,61 @@ impl Reply {
             .collect()
     }
 
-    pub fn render_template<S: Serialize>(
+    /// Whether the reply is within its activation windows and matches the stream condition.
+    /// Daily windows are evaluated in the channel timezone and can span midnight.
+    /// Replies with a stream condition are inactive until the stream status is known.
+    pub fn is_active(&self, now: DateTime<Utc>, timezone: Tz, is_live: Option<bool>) -> bool {
+        if self.active_from.is_some_and(|active_from| now < active_from)
+            || self.active_until.is_some_and(|active_until| now >= active_until)
+        {
+            return false;
+        }
+
+        let time = now.with_timezone(&timezone).time();
+        let in_daily_window = match (self.daily_active_from, self.daily_active_until) {
+            (Some(from), Some(until)) if from <= until => from <= time && time < until,
+            (Some(from), Some(until)) => from <= time || time < until,
+            (Some(from), None) => from <= time,
+            (None, Some(until)) => time < until,
+            (None, None) => true,
+        };
+        if !in_daily_window {
+            return false;
+        }
+
+        matches!(
+            (self.stream_condition, is_live),
+            (None, _) | (Some(StreamCondition::Live), Some(true)) | (Some(StreamCondition::Offline), Some(false))
+        )
+    }
+
+    /// Named capture groups of the reply pattern matched against the given message.
+    pub fn captures(&self, matchable_message: &impl MatchableMessage) -> HashMap<String, String> {
+        let Ok(re) = RegexBuilder::new(&self.pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()
+        else {
+            return HashMap::new();
+        };
+        let Some(captures) = re.captures(matchable_message.text()) else {
+            return HashMap::new();
+        };
+
+        re.capture_names()
+            .flatten()
+            .filter_map(|name| captures.name(name).map(|m| (name.to_owned(), m.as_str().to_owned())))
+            .collect()
+    }
+
+    pub async fn render_template<S: Serialize>(
         &self,
-        template_env: &Environment,
+        template_env: &Environment<'_>,
+        channel: &Channel,
+        streams: &Streams,
+        db_pool: &SqlitePool,
         ctx: Option<&S>,
     ) -> Result<String, minijinja::Error> {
-        let ctx = match ctx {
-            Some(ctx) => minijinja::value::Value::from_serialize(ctx),
-            None => context!(),
-        };
-        template_env.render_str(&self.template, ctx).map(|s| s.trim().into())
+        templates_env::render_template(template_env, &self.template, channel, streams, db_pool, ctx).await
     }
 
     async fn all<'a>(
@@ -103,6 +168,11 @@ impl Reply {
                     enabled,
                     created_by,
                     additional_inputs as "additional_inputs: Json<serde_json::Value>",
+                    active_from as "active_from: DateTime<Utc>",
+                    active_until as "active_until: DateTime<Utc>",
+                    daily_active_from as "daily_active_from: NaiveTime",
+                    daily_active_until as "daily_active_until: NaiveTime",
+                    stream_condition as "stream_condition: StreamCondition",
                     created_at as "created_at!: DateTime<Utc>",
                     updated_at as "updated_at!: DateTime<Utc>"
                 from replies
@@ -117,19 +187,136 @@ impl Reply {
     }
 }
 
+/// Trimmed value of the given named capture, if not empty.
+pub fn capture<'a>(captures: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    captures.get(name).map(|c| c.trim()).filter(|c| !c.is_empty())
+}
+
+/// Like [`capture`] but failing if missing.
+pub fn required_capture<'a>(captures: &'a HashMap<String, String>, name: &str) -> anyhow::Result<&'a str> {
+    capture(captures, name).ok_or_else(|| anyhow!("Missing {:?} capture in {:?}.", name, captures))
+}
+
+#[cfg(test)]
+pub fn test_captures(xs: &[(&str, &str)]) -> HashMap<String, String> {
+    xs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+/// In memory DB with all the migrations applied.
+#[cfg(test)]
+pub async fn test_db_pool() -> SqlitePool {
+    let db_pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("../../migrations").run(&db_pool).await.unwrap();
+    db_pool
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Channel {
+    pub name: String,
+    pub caster: String,
+    pub date_of_birth: Option<NaiveDate>,
+    /// Missing without a channel profile, to not override the timezone of the templates inputs.
+    pub timezone: Option<String>,
+    pub seven_tv_id: Option<String>,
+}
+
+impl Channel {
+    pub async fn by_name<'a>(name: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    name as "name!",
+                    caster,
+                    date_of_birth as "date_of_birth: NaiveDate",
+                    timezone as "timezone?",
+                    seven_tv_id
+                from channels
+                where name = $1
+            "#,
+            name,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Channel profile of the given channel, falling back to one without timezone if missing.
+    pub async fn by_name_or_default<'a>(name: &str, executor: impl SqliteExecutor<'a>) -> Self {
+        match Self::by_name(name, executor).await {
+            Ok(Some(channel)) => channel,
+            Ok(None) => Self::default_for(name),
+            Err(error) => {
+                eprintln!("Error loading channel {:?}, error: {:?}.", name, error);
+                Self::default_for(name)
+            }
+        }
+    }
+
+    /// UTC if missing or invalid.
+    pub fn tz(&self) -> Tz {
+        let Some(timezone) = &self.timezone else {
+            return Tz::UTC;
+        };
+        Tz::from_str(timezone).unwrap_or_else(|error| {
+            eprintln!("Invalid timezone for channel {:?}, error: {:?}.", self, error);
+            Tz::UTC
+        })
+    }
+
+    fn default_for(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            caster: name.into(),
+            date_of_birth: None,
+            timezone: None,
+            seven_tv_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+pub enum StreamCondition {
+    Live,
+    Offline,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "TEXT")]
 pub enum Handler {
+    ChannelInfoCommands,
+    ChatActivity,
+    ChatEvents,
+    Counters,
+    Followage,
     Gamba,
+    GambaCommands,
+    GambaStats,
     Gg,
     Npc,
+    Poll,
+    PollCommands,
+    Points,
+    Quotes,
+    Raffles,
+    Reminders,
     RipBozo,
+    Shoutout,
     Sniffa,
+    StreamMarkers,
     TheGrind,
+    Trivia,
+    TriviaCommands,
+    Vars,
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::TimeZone;
     use fake::Fake;
     use fake::Faker;
     use twitch_irc::message::IRCMessage;
@@ -139,6 +326,83 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn reply_captures_works_as_expected() {
+        let reply = dummy_reply(r"^!setcounter (?P<name>\w+)(?: (?P<value>-?\d+))?$".into());
+
+        assert_eq!(
+            HashMap::from([
+                ("name".to_owned(), "deaths".to_owned()),
+                ("value".to_owned(), "-3".to_owned())
+            ]),
+            reply.captures(&dummy_privmsg_message("!setcounter deaths -3".into(), None))
+        );
+        assert_eq!(
+            HashMap::from([("name".to_owned(), "deaths".to_owned())]),
+            reply.captures(&dummy_privmsg_message("!SETCOUNTER deaths".into(), None))
+        );
+        assert!(reply
+            .captures(&dummy_privmsg_message("!setcounter".into(), None))
+            .is_empty());
+    }
+
+    #[test]
+    fn reply_is_active_works_as_expected() {
+        let now = Utc.with_ymd_and_hms(2023, 4, 16, 22, 30, 0).unwrap();
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0);
+        let reply = dummy_reply(Faker.fake());
+
+        assert!(reply.is_active(now, Tz::UTC, None));
+        assert!(Reply {
+            active_from: Some(now),
+            active_until: Some(now + chrono::Duration::days(1)),
+            ..reply.clone()
+        }
+        .is_active(now, Tz::UTC, None));
+        assert!(!Reply {
+            active_from: Some(now + chrono::Duration::seconds(1)),
+            ..reply.clone()
+        }
+        .is_active(now, Tz::UTC, None));
+        assert!(!Reply {
+            active_until: Some(now),
+            ..reply.clone()
+        }
+        .is_active(now, Tz::UTC, None));
+
+        let daily = Reply {
+            daily_active_from: time(20, 0),
+            daily_active_until: time(23, 0),
+            ..reply.clone()
+        };
+        assert!(daily.is_active(now, Tz::UTC, None));
+        // 00:30 in Berlin
+        assert!(!daily.is_active(now, Tz::Europe__Berlin, None));
+
+        let overnight = Reply {
+            daily_active_from: time(23, 0),
+            daily_active_until: time(1, 0),
+            ..reply.clone()
+        };
+        assert!(!overnight.is_active(now, Tz::UTC, None));
+        assert!(overnight.is_active(now, Tz::Europe__Berlin, None));
+
+        let live_only = Reply {
+            stream_condition: Some(StreamCondition::Live),
+            ..reply.clone()
+        };
+        assert!(live_only.is_active(now, Tz::UTC, Some(true)));
+        assert!(!live_only.is_active(now, Tz::UTC, Some(false)));
+        assert!(!live_only.is_active(now, Tz::UTC, None));
+
+        let offline_only = Reply {
+            stream_condition: Some(StreamCondition::Offline),
+            ..reply
+        };
+        assert!(!offline_only.is_active(now, Tz::UTC, Some(true)));
+        assert!(offline_only.is_active(now, Tz::UTC, Some(false)));
+    }
+
     #[test]
     fn matchable_message_text_works_as_expected() {
         assert_eq!("@foo bar", dummy_privmsg_message("@foo bar".into(), None).text());
@@ -152,6 +416,27 @@ mod tests {
         )
     }
 
+    fn dummy_reply(pattern: String) -> Reply {
+        Reply {
+            id: Faker.fake(),
+            handler: None,
+            pattern,
+            case_insensitive: true,
+            template: Faker.fake(),
+            channel: None,
+            enabled: true,
+            additional_inputs: None,
+            active_from: None,
+            active_until: None,
+            daily_active_from: None,
+            daily_active_until: None,
+            stream_condition: None,
+            created_by: Faker.fake(),
+            created_at: Faker.fake(),
+            updated_at: Faker.fake(),
+        }
+    }
+
     fn dummy_privmsg_message(message_text: String, reply_parent_user_name: Option<String>) -> PrivmsgMessage {
         PrivmsgMessage {
             channel_login: Faker.fake(),
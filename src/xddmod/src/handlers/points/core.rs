@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
+use minijinja::value::Value;
+use minijinja::Environment;
+use rand::Rng;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::points::persistence::PointsSettings;
+use crate::handlers::points::persistence::UserPoints;
+use crate::handlers::rip_bozo::core::Mentions;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+pub const TICK: Duration = Duration::from_secs(60);
+pub const LEADERBOARD_LEN: i64 = 5;
+pub const SLOTS_SYMBOLS: [&str; 5] = ["🍒", "🍋", "🔔", "💎", "7️⃣"];
+/// Multiplier of the bet paid back when all the reels match.
+pub const SLOTS_JACKPOT_MULTIPLIER: i64 = 10;
+
+/// Chat loyalty points earned while live and spent in chat games, e.g. `!points`, `!leaderboard`,
+/// `!roulette <amount>`, `!slots <amount>`, `!duel @user <amount>` and `!accept`.
+///
+/// Opt-in per channel through its points settings. Points are earned per message, at most once every
+/// `message_cooldown_secs`, and per minute while active in chat.
+/// The reply pattern must capture the `action` (`points`, `leaderboard`, `roulette`, `slots`, `duel` or `accept`) and,
+/// for the games, the `amount` (a number or `all`).
+/// Complements [`crate::handlers::gamba_time::core::GambaTime`], which only covers Twitch predictions.
+pub struct Points<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+    activities: Mutex<HashMap<(String, String), Activity>>,
+    duels: Mutex<HashMap<(String, String), Duel>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Activity {
+    user_name: String,
+    last_message_at: DateTime<Utc>,
+    last_rewarded_message_at: Option<DateTime<Utc>>,
+}
+
+/// Challenge waiting for the acceptance of the target.
+#[derive(Debug, Clone, PartialEq)]
+struct Duel {
+    challenger_login: String,
+    challenger_name: String,
+    amount: i64,
+    challenged_at: DateTime<Utc>,
+}
+
+impl<'a> Points<'a> {
+    pub fn new(outbox: Outbox, streams: Streams, db_pool: SqlitePool, templates_env: Environment<'a>) -> Self {
+        Self {
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            activities: Mutex::new(HashMap::new()),
+            duels: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn handler(&self) -> Handler {
+        Handler::Points
+    }
+}
+
+impl Points<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            let settings = match PointsSettings::enabled_by_channel(&message.channel_login, &self.db_pool).await {
+                Ok(Some(settings)) => settings,
+                Ok(None) => return,
+                Err(error) => {
+                    eprintln!(
+                        "Error loading points settings of channel {:?}, error: {:?}.",
+                        message.channel_login, error
+                    );
+                    return;
+                }
+            };
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            if is_live == Some(true) {
+                self.earn_per_message(&settings, message).await;
+            }
+
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let points_command = match PointsCommand::try_from(&reply.captures(message)) {
+                        Ok(points_command) => points_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing points command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(&settings, message, &points_command).await {
+                        Ok(Some(template_inputs)) => template_inputs,
+                        Ok(None) => return,
+                        Err(error) => {
+                            eprintln!(
+                                "Error executing points command {:?}, error: {:?}.",
+                                points_command, error
+                            );
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    /// Rewards the users active in the chat of the live channels every [`TICK`].
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            self.earn_per_active_minute(Utc::now()).await;
+        }
+    }
+
+    async fn earn_per_message(&self, settings: &PointsSettings, message: &PrivmsgMessage) {
+        let now = Utc::now();
+        let is_rewarded = match self.activities.lock() {
+            Ok(mut activities) => {
+                let activity = activities
+                    .entry((message.channel_login.clone(), message.sender.login.clone()))
+                    .or_insert_with(|| Activity {
+                        user_name: message.sender.name.clone(),
+                        last_message_at: now,
+                        last_rewarded_message_at: None,
+                    });
+                activity.user_name = message.sender.name.clone();
+                activity.last_message_at = now;
+
+                let is_rewarded = activity
+                    .last_rewarded_message_at
+                    .is_none_or(|at| (now - at).num_seconds() >= settings.message_cooldown_secs);
+                if is_rewarded {
+                    activity.last_rewarded_message_at = Some(now);
+                }
+                is_rewarded
+            }
+            Err(error) => {
+                eprintln!("Cannot get points activities Lock, error: {:?}", error);
+                false
+            }
+        };
+
+        if is_rewarded && settings.per_message > 0 {
+            if let Err(error) = UserPoints::add(
+                &message.channel_login,
+                &message.sender.login,
+                &message.sender.name,
+                settings.per_message,
+                &self.db_pool,
+            )
+            .await
+            {
+                eprintln!("Error adding message points {:?}, error: {:?}", message.sender, error);
+            }
+        }
+    }
+
+    async fn earn_per_active_minute(&self, now: DateTime<Utc>) {
+        let all_settings = match PointsSettings::all_enabled(&self.db_pool).await {
+            Ok(all_settings) => all_settings,
+            Err(error) => {
+                eprintln!("Error loading points settings, error: {:?}", error);
+                return;
+            }
+        };
+
+        match self.activities.lock() {
+            Ok(mut activities) => evict_inactive(&mut activities, &all_settings, now),
+            Err(error) => {
+                eprintln!("Cannot get points activities Lock, error: {:?}", error);
+                return;
+            }
+        }
+
+        for settings in all_settings {
+            if self.streams.is_live(&settings.channel) != Some(true) || settings.per_active_minute <= 0 {
+                continue;
+            }
+
+            let active_users: Vec<(String, String)> = match self.activities.lock() {
+                Ok(activities) => activities
+                    .iter()
+                    .filter(|((channel, _), activity)| {
+                        *channel == settings.channel
+                            && (now - activity.last_message_at).num_seconds() <= settings.active_window_secs
+                    })
+                    .map(|((_, user_login), activity)| (user_login.clone(), activity.user_name.clone()))
+                    .collect(),
+                Err(error) => {
+                    eprintln!("Cannot get points activities Lock, error: {:?}", error);
+                    return;
+                }
+            };
+
+            for (user_login, user_name) in active_users {
+                if let Err(error) = UserPoints::add(
+                    &settings.channel,
+                    &user_login,
+                    &user_name,
+                    settings.per_active_minute,
+                    &self.db_pool,
+                )
+                .await
+                {
+                    eprintln!("Error adding active minute points {:?}, error: {:?}", user_login, error);
+                }
+            }
+        }
+    }
+
+    /// `None` when there is nothing to reply, e.g. accepting an expired duel.
+    async fn execute(
+        &self,
+        settings: &PointsSettings,
+        message: &PrivmsgMessage,
+        points_command: &PointsCommand,
+    ) -> anyhow::Result<Option<TemplateInputs>> {
+        let channel = message.channel_login.as_str();
+        let sender = &message.sender;
+        let mentioned_login = Mentions::new(&message.message_text)
+            .as_inner()
+            .first()
+            .map(|m| m.login().to_lowercase());
+
+        let mut template_inputs = TemplateInputs {
+            action: points_command.action(),
+            user: sender.name.clone(),
+            ..Default::default()
+        };
+
+        match points_command {
+            PointsCommand::Balance => {
+                let login = mentioned_login.as_deref().unwrap_or(&sender.login);
+                template_inputs.target = mentioned_login.clone();
+                template_inputs.balance = Some(self.balance(channel, login).await?);
+            }
+            PointsCommand::Leaderboard => {
+                template_inputs.leaderboard =
+                    UserPoints::top_by_channel(channel, LEADERBOARD_LEN, &self.db_pool).await?;
+            }
+            PointsCommand::Roulette { amount } => {
+                let amount = self.bet(settings, channel, &sender.login, amount).await?;
+                let Some(user_points) = UserPoints::spend(channel, &sender.login, amount, &self.db_pool).await? else {
+                    bail!("Not enough points for roulette {:?}.", sender);
+                };
+
+                let won = rand::thread_rng().gen_range(0..100) < settings.roulette_win_pct;
+                let user_points = match won {
+                    true => UserPoints::add(channel, &sender.login, &sender.name, amount * 2, &self.db_pool).await?,
+                    false => user_points,
+                };
+
+                template_inputs.amount = Some(amount);
+                template_inputs.won = Some(won);
+                template_inputs.balance = Some(user_points.balance);
+            }
+            PointsCommand::Slots { amount } => {
+                let amount = self.bet(settings, channel, &sender.login, amount).await?;
+                let Some(user_points) = UserPoints::spend(channel, &sender.login, amount, &self.db_pool).await? else {
+                    bail!("Not enough points for slots {:?}.", sender);
+                };
+
+                let reels = spin(&mut rand::thread_rng());
+                let payout = slots_payout(&reels, amount);
+                let user_points = match payout {
+                    0 => user_points,
+                    payout => UserPoints::add(channel, &sender.login, &sender.name, payout, &self.db_pool).await?,
+                };
+
+                template_inputs.amount = Some(amount);
+                template_inputs.won = Some(payout > amount);
+                template_inputs.reels = reels.to_vec();
+                template_inputs.payout = Some(payout);
+                template_inputs.balance = Some(user_points.balance);
+            }
+            PointsCommand::Duel { amount } => {
+                let target_login = mentioned_login.ok_or_else(|| anyhow!("Missing duel target {:?}.", message))?;
+                if target_login == sender.login {
+                    bail!("Cannot duel themselves {:?}.", sender);
+                }
+                let amount = self.bet(settings, channel, &sender.login, amount).await?;
+                if self.balance(channel, &target_login).await? < amount {
+                    bail!("Not enough points for duel of {:?}.", target_login);
+                }
+
+                let duel = Duel {
+                    challenger_login: sender.login.clone(),
+                    challenger_name: sender.name.clone(),
+                    amount,
+                    challenged_at: Utc::now(),
+                };
+                self.duels
+                    .lock()
+                    .map_err(|error| anyhow!("Cannot get duels Lock, error: {:?}", error))?
+                    .insert((channel.into(), target_login.clone()), duel);
+
+                template_inputs.target = Some(target_login);
+                template_inputs.amount = Some(amount);
+            }
+            PointsCommand::Accept => {
+                let duel = self
+                    .duels
+                    .lock()
+                    .map_err(|error| anyhow!("Cannot get duels Lock, error: {:?}", error))?
+                    .remove(&(channel.into(), sender.login.clone()))
+                    .filter(|d| (Utc::now() - d.challenged_at).num_seconds() <= settings.duel_timeout_secs);
+                let Some(duel) = duel else {
+                    return Ok(None);
+                };
+
+                let mut tx = self.db_pool.begin().await?;
+                if UserPoints::spend(channel, &duel.challenger_login, duel.amount, &mut *tx)
+                    .await?
+                    .is_none()
+                    || UserPoints::spend(channel, &sender.login, duel.amount, &mut *tx)
+                        .await?
+                        .is_none()
+                {
+                    tx.rollback().await?;
+                    bail!("Not enough points for duel {:?}.", duel);
+                }
+                let (winner_login, winner_name) = match rand::thread_rng().gen_bool(0.5) {
+                    true => (duel.challenger_login.as_str(), duel.challenger_name.as_str()),
+                    false => (sender.login.as_str(), sender.name.as_str()),
+                };
+                UserPoints::add(channel, winner_login, winner_name, duel.amount * 2, &mut *tx).await?;
+                tx.commit().await?;
+
+                template_inputs.target = Some(duel.challenger_login.clone());
+                template_inputs.amount = Some(duel.amount);
+                template_inputs.winner = Some(winner_name.into());
+                template_inputs.won = Some(winner_login == sender.login);
+            }
+        }
+
+        Ok(Some(template_inputs))
+    }
+
+    async fn balance(&self, channel: &str, user_login: &str) -> anyhow::Result<i64> {
+        Ok(UserPoints::get(channel, user_login, &self.db_pool)
+            .await?
+            .map(|p| p.balance)
+            .unwrap_or_default())
+    }
+
+    /// Resolves the amount against the balance of the user and the max bet of the channel.
+    async fn bet(
+        &self,
+        settings: &PointsSettings,
+        channel: &str,
+        user_login: &str,
+        amount: &Amount,
+    ) -> anyhow::Result<i64> {
+        let balance = self.balance(channel, user_login).await?;
+        let amount = match amount {
+            Amount::All => balance,
+            Amount::Points(points) => *points,
+        };
+        let amount = match settings.max_bet {
+            max_bet if max_bet > 0 => amount.min(max_bet),
+            _ => amount,
+        };
+
+        if amount <= 0 || amount > balance {
+            bail!(
+                "Invalid bet {:?} with balance {:?} for {:?}.",
+                amount,
+                balance,
+                user_login
+            );
+        }
+        Ok(amount)
+    }
+}
+
+/// Drops the users neither active nor in their message cooldown anymore, so that they don't pile up.
+fn evict_inactive(
+    activities: &mut HashMap<(String, String), Activity>,
+    all_settings: &[PointsSettings],
+    now: DateTime<Utc>,
+) {
+    activities.retain(|(channel, _), activity| {
+        all_settings.iter().any(|settings| {
+            settings.channel == *channel
+                && (now - activity.last_message_at).num_seconds()
+                    <= settings.active_window_secs.max(settings.message_cooldown_secs)
+        })
+    });
+}
+
+fn spin<R: Rng + ?Sized>(rng: &mut R) -> [&'static str; 3] {
+    [(); 3].map(|_| SLOTS_SYMBOLS[rng.gen_range(0..SLOTS_SYMBOLS.len())])
+}
+
+/// Points paid back: the jackpot for three matching reels, the bet for two, nothing otherwise.
+fn slots_payout(reels: &[&str; 3], amount: i64) -> i64 {
+    match reels {
+        [a, b, c] if a == b && b == c => amount * SLOTS_JACKPOT_MULTIPLIER,
+        [a, b, c] if a == b || b == c || a == c => amount,
+        _ => 0,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Amount {
+    All,
+    Points(i64),
+}
+
+impl std::str::FromStr for Amount {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "all" | "allin" | "all-in" => Ok(Self::All),
+            points => Ok(Self::Points(points.parse()?)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointsCommand {
+    Balance,
+    Leaderboard,
+    Roulette { amount: Amount },
+    Slots { amount: Amount },
+    Duel { amount: Amount },
+    Accept,
+}
+
+impl PointsCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Balance => "points",
+            Self::Leaderboard => "leaderboard",
+            Self::Roulette { .. } => "roulette",
+            Self::Slots { .. } => "slots",
+            Self::Duel { .. } => "duel",
+            Self::Accept => "accept",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for PointsCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        let amount = || -> anyhow::Result<Amount> { required_capture(captures, "amount")?.parse() };
+
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "points" | "balance" => Self::Balance,
+            "leaderboard" | "top" => Self::Leaderboard,
+            "roulette" | "gamble" => Self::Roulette { amount: amount()? },
+            "slots" => Self::Slots { amount: amount()? },
+            "duel" => Self::Duel { amount: amount()? },
+            "accept" => Self::Accept,
+            unexpected_action => bail!("Unexpected points action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    /// The sender.
+    user: String,
+    /// The mentioned user or the challenger of the accepted duel.
+    target: Option<String>,
+    amount: Option<i64>,
+    /// Balance of the target or the sender after the game.
+    balance: Option<i64>,
+    won: Option<bool>,
+    winner: Option<String>,
+    reels: Vec<&'static str>,
+    payout: Option<i64>,
+    leaderboard: Vec<UserPoints>,
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_points_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            PointsCommand::Balance,
+            PointsCommand::try_from(&test_captures(&[("action", "points")])).unwrap()
+        );
+        assert_eq!(
+            PointsCommand::Roulette {
+                amount: Amount::Points(42)
+            },
+            PointsCommand::try_from(&test_captures(&[("action", "roulette"), ("amount", " 42 ")])).unwrap()
+        );
+        assert_eq!(
+            PointsCommand::Slots { amount: Amount::All },
+            PointsCommand::try_from(&test_captures(&[("action", "Slots"), ("amount", "ALL")])).unwrap()
+        );
+        assert_eq!(
+            PointsCommand::Duel {
+                amount: Amount::Points(100)
+            },
+            PointsCommand::try_from(&test_captures(&[("action", "duel"), ("amount", "100")])).unwrap()
+        );
+        assert!(PointsCommand::try_from(&test_captures(&[("action", "duel")])).is_err());
+        assert!(PointsCommand::try_from(&test_captures(&[("action", "roulette"), ("amount", "foo")])).is_err());
+        assert!(PointsCommand::try_from(&test_captures(&[("action", "foo")])).is_err());
+    }
+
+    #[test]
+    fn test_slots_payout_works_as_expected() {
+        assert_eq!(10 * SLOTS_JACKPOT_MULTIPLIER, slots_payout(&["💎", "💎", "💎"], 10));
+        assert_eq!(10, slots_payout(&["💎", "🍒", "💎"], 10));
+        assert_eq!(0, slots_payout(&["💎", "🍒", "🍋"], 10));
+    }
+
+    #[test]
+    fn test_spin_picks_slots_symbols() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..100 {
+            assert!(spin(&mut rng).iter().all(|s| SLOTS_SYMBOLS.contains(s)));
+        }
+    }
+
+    #[test]
+    fn test_evict_inactive_works_as_expected() {
+        let now = Utc::now();
+        let activity = |secs_ago: i64| Activity {
+            user_name: "Bar".into(),
+            last_message_at: now - chrono::Duration::seconds(secs_ago),
+            last_rewarded_message_at: None,
+        };
+        let mut activities = HashMap::from([
+            (("foo".into(), "active".into()), activity(60)),
+            (("foo".into(), "cooling_down".into()), activity(500)),
+            (("foo".into(), "inactive".into()), activity(700)),
+            (("baz".into(), "disabled".into()), activity(0)),
+        ]);
+        let settings = PointsSettings {
+            channel: "foo".into(),
+            per_message: 1,
+            message_cooldown_secs: 600,
+            per_active_minute: 1,
+            active_window_secs: 300,
+            roulette_win_pct: 50,
+            duel_timeout_secs: 60,
+            max_bet: 0,
+            enabled: true,
+            created_by: "bar".into(),
+            created_at: now,
+            updated_at: now,
+        };
+
+        evict_inactive(&mut activities, &[settings], now);
+
+        let mut users: Vec<&str> = activities.keys().map(|(_, user)| user.as_str()).collect();
+        users.sort();
+        assert_eq!(vec!["active", "cooling_down"], users);
+    }
+}
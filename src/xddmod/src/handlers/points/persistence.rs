@@ -0,0 +1,207 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+/// Per channel tuning of the loyalty points.
+#[derive(Debug, Clone)]
+pub struct PointsSettings {
+    pub channel: String,
+    pub per_message: i64,
+    /// Minimum seconds between two rewarded messages of the same user.
+    pub message_cooldown_secs: i64,
+    pub per_active_minute: i64,
+    /// Users are active for this many seconds after their last message.
+    pub active_window_secs: i64,
+    pub roulette_win_pct: i64,
+    pub duel_timeout_secs: i64,
+    /// 0 means no limit.
+    pub max_bet: i64,
+    pub enabled: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserPoints {
+    pub channel: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub balance: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PointsSettings {
+    pub async fn enabled_by_channel<'a>(
+        channel: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    per_message,
+                    message_cooldown_secs,
+                    per_active_minute,
+                    active_window_secs,
+                    roulette_win_pct,
+                    duel_timeout_secs,
+                    max_bet,
+                    enabled,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from points_settings
+                where channel = $1 and enabled = 1
+            "#,
+            channel,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn all_enabled<'a>(executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    per_message,
+                    message_cooldown_secs,
+                    per_active_minute,
+                    active_window_secs,
+                    roulette_win_pct,
+                    duel_timeout_secs,
+                    max_bet,
+                    enabled,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from points_settings
+                where enabled = 1
+            "#,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+impl UserPoints {
+    pub async fn get<'a>(
+        channel: &str,
+        user_login: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    user_login,
+                    user_name,
+                    balance,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from points
+                where channel = $1 and user_login = $2
+            "#,
+            channel,
+            user_login,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn add<'a>(
+        channel: &str,
+        user_login: &str,
+        user_name: &str,
+        amount: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into points (channel, user_login, user_name, balance)
+                values ($1, $2, $3, $4)
+                on conflict (channel, user_login) do update set
+                    user_name = excluded.user_name,
+                    balance = balance + excluded.balance,
+                    updated_at = current_timestamp
+                returning
+                    channel,
+                    user_login,
+                    user_name,
+                    balance,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            user_login,
+            user_name,
+            amount,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// `None` if the user has not enough points.
+    pub async fn spend<'a>(
+        channel: &str,
+        user_login: &str,
+        amount: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                update points
+                set balance = balance - $3, updated_at = current_timestamp
+                where channel = $1 and user_login = $2 and balance >= $3
+                returning
+                    channel,
+                    user_login,
+                    user_name,
+                    balance,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            user_login,
+            amount,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn top_by_channel<'a>(
+        channel: &str,
+        limit: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    user_login,
+                    user_name,
+                    balance,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from points
+                where channel = $1
+                order by balance desc
+                limit $2
+            "#,
+            channel,
+            limit,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
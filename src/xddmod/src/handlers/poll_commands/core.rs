@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use chrono::Utc;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::PollStatus;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::apis::twitch::polls;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::poll_time::core::Poll;
+use crate::handlers::poll_time::core::PollState;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+pub const DEFAULT_DURATION_SECS: i64 = 60;
+pub const CHOICES_SEPARATOR: char = '|';
+
+/// Mod commands to create and end polls.
+///
+/// The reply pattern must capture the `action` (`create` or `end`).
+/// `create` also needs the `title` and the `|` separated `choices` plus, optionally, the `duration` in seconds.
+/// `end` terminates the running poll showing its results.
+///
+/// Requires the broadcaster token with the `channel:manage:polls` scope.
+pub struct PollCommands<'a> {
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl PollCommands<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::PollCommands
+    }
+}
+
+impl PollCommands<'_> {
+    pub async fn handle(&mut self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let poll_command = match PollCommand::try_from(&reply.captures(message)) {
+                        Ok(poll_command) => poll_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing poll command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(&poll_command).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!("Error executing poll command {:?}, error: {:?}.", poll_command, error);
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn execute(&mut self, poll_command: &PollCommand) -> anyhow::Result<TemplateInputs> {
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for poll commands");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        match poll_command {
+            PollCommand::Create {
+                title,
+                choices,
+                duration_secs,
+            } => {
+                polls::create_poll(
+                    &self.helix_client,
+                    &self.token,
+                    &self.broadcaster_id,
+                    title,
+                    choices,
+                    *duration_secs,
+                )
+                .await?;
+
+                Ok(TemplateInputs {
+                    action: poll_command.action(),
+                    title: title.clone(),
+                    choices: choices.clone(),
+                    poll: None,
+                })
+            }
+            PollCommand::End => {
+                let helix_poll = polls::get_last_poll(&self.helix_client, &self.token, &self.broadcaster_id)
+                    .await?
+                    .filter(|p| p.status == PollStatus::Active)
+                    .ok_or_else(|| anyhow!("No active Poll found for broadcaster {:?}.", self.broadcaster_id))?;
+
+                polls::end_poll(
+                    &self.helix_client,
+                    &self.token,
+                    &self.broadcaster_id,
+                    &helix_poll,
+                    PollStatus::Terminated,
+                )
+                .await?;
+
+                let poll = Poll {
+                    state: PollState::Terminated { ended_at: Utc::now() },
+                    ..Poll::try_from(helix_poll)?
+                };
+                Ok(TemplateInputs {
+                    action: poll_command.action(),
+                    title: poll.title.clone(),
+                    choices: poll.choices.iter().map(|c| c.title.clone()).collect(),
+                    poll: Some(poll),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum PollCommand {
+    Create {
+        title: String,
+        choices: Vec<String>,
+        duration_secs: i64,
+    },
+    End,
+}
+
+impl PollCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Create { .. } => "create",
+            Self::End => "end",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for PollCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "create" | "start" => {
+                let choices: Vec<String> = required_capture(captures, "choices")?
+                    .split(CHOICES_SEPARATOR)
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(String::from)
+                    .collect();
+                if !(2..=5).contains(&choices.len()) {
+                    bail!("Expected between 2 and 5 choices, got {:?}.", choices);
+                }
+
+                let duration_secs = match captures.get("duration") {
+                    Some(duration) => i64::from_str(duration.trim())?,
+                    None => DEFAULT_DURATION_SECS,
+                };
+                if !(15..=1800).contains(&duration_secs) {
+                    bail!(
+                        "Expected duration between 15 and 1800 seconds, got {:?}.",
+                        duration_secs
+                    );
+                }
+
+                Self::Create {
+                    title: required_capture(captures, "title")?.into(),
+                    choices,
+                    duration_secs,
+                }
+            }
+            "end" | "stop" => Self::End,
+            unexpected_action => bail!("Unexpected poll action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    title: String,
+    choices: Vec<String>,
+    /// The ended poll with its results.
+    poll: Option<Poll>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_poll_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            PollCommand::Create {
+                title: "Next game?".into(),
+                choices: vec!["League".into(), "TFT".into(), "Valorant".into()],
+                duration_secs: 120,
+            },
+            PollCommand::try_from(&test_captures(&[
+                ("action", "create"),
+                ("title", "Next game?"),
+                ("choices", "League | TFT | Valorant"),
+                ("duration", "120")
+            ]))
+            .unwrap()
+        );
+        assert_eq!(
+            PollCommand::Create {
+                title: "Next game?".into(),
+                choices: vec!["League".into(), "TFT".into()],
+                duration_secs: DEFAULT_DURATION_SECS,
+            },
+            PollCommand::try_from(&test_captures(&[
+                ("action", "Start"),
+                ("title", "Next game?"),
+                ("choices", "League|TFT")
+            ]))
+            .unwrap()
+        );
+        assert!(PollCommand::try_from(&test_captures(&[
+            ("action", "create"),
+            ("title", "Next game?"),
+            ("choices", "a|b|c|d|e|f")
+        ]))
+        .is_err());
+        assert!(PollCommand::try_from(&test_captures(&[
+            ("action", "create"),
+            ("title", "Next game?"),
+            ("choices", "a|b"),
+            ("duration", "1")
+        ]))
+        .is_err());
+        assert!(PollCommand::try_from(&test_captures(&[("action", "create"), ("choices", "a|b")])).is_err());
+        assert_eq!(
+            PollCommand::End,
+            PollCommand::try_from(&test_captures(&[("action", "end")])).unwrap()
+        );
+        assert!(PollCommand::try_from(&test_captures(&[("action", "foo")])).is_err());
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
+use fake::Dummy;
+use fake::Fake;
+use fake::Faker;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::helix::polls::Poll as HelixPoll;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::PollChoice;
+use twitch_api::types::PollStatus;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch::eventsub::Event;
+use crate::apis::twitch::eventsub::PollEventChoice;
+use crate::apis::twitch::polls;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::MatchableMessage;
+use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Renders the current poll on chat commands and announces its begin and end.
+///
+/// Announcements are driven by EventSub poll events and matched against `poll.begin` and `poll.end`.
+/// The latest poll received by EventSub is cached, so chat commands hit Helix only when nothing has been
+/// received yet.
+pub struct PollTime<'a> {
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+    last_polls: Mutex<HashMap<String, Poll>>,
+}
+
+impl<'a> PollTime<'a> {
+    pub fn new(
+        token: UserToken,
+        broadcaster_id: UserId,
+        helix_client: HelixClient<'a, reqwest::Client>,
+        outbox: Outbox,
+        streams: Streams,
+        db_pool: SqlitePool,
+        templates_env: Environment<'a>,
+    ) -> Self {
+        Self {
+            token,
+            broadcaster_id,
+            helix_client,
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            last_polls: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl PollTime<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::Poll
+    }
+}
+
+impl PollTime<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let Some(poll) = self.current_poll(&message.channel_login).await else {
+                        return;
+                    };
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&poll)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    pub async fn handle_event(&self, event: &Event) {
+        let poll_event_kind = match event {
+            Event::PollBegin(_) => Some("poll.begin"),
+            Event::PollProgress(_) => None,
+            Event::PollEnd(_) => Some("poll.end"),
+            _ => return,
+        };
+
+        let poll = match Poll::try_from(event) {
+            Ok(poll) => poll,
+            Err(e) => {
+                eprintln!("Error building Poll for event {:?}, error: {:?}.", event, e);
+                return;
+            }
+        };
+
+        let channel_login = event.broadcaster_user_login().to_owned();
+        match self.last_polls.lock() {
+            Ok(mut last_polls) => {
+                last_polls.insert(channel_login.clone(), poll.clone());
+            }
+            Err(error) => eprintln!("Cannot get last polls Lock, error: {:?}", error),
+        }
+
+        let Some(poll_event_kind) = poll_event_kind else {
+            return;
+        };
+        let poll_event = PollTimeEvent {
+            channel: channel_login,
+            kind: poll_event_kind,
+        };
+
+        let channel = Channel::by_name_or_default(&poll_event.channel, &self.db_pool).await;
+        let is_live = self.streams.is_live(&poll_event.channel);
+        match Reply::matching(self.handler(), &poll_event, &channel, is_live, &self.db_pool)
+            .await
+            .as_slice()
+        {
+            [reply] => {
+                match reply
+                    .render_template(
+                        &self.templates_env,
+                        &channel,
+                        &self.streams,
+                        &self.db_pool,
+                        Some(&Value::from_serialize(&poll)),
+                    )
+                    .await
+                {
+                    Ok(rendered_reply) if rendered_reply.is_empty() => {
+                        eprintln!("Rendered reply template empty: {:?}.", reply)
+                    }
+                    Ok(rendered_reply) => self.outbox.say(&poll_event.channel, rendered_reply),
+                    Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                }
+            }
+            [] => {}
+            multiple_matching_replies => eprintln!(
+                "Multiple matching replies for poll event: {:?}, {:?}.",
+                multiple_matching_replies, poll_event
+            ),
+        }
+    }
+
+    async fn current_poll(&self, channel: &str) -> Option<Poll> {
+        let last_poll = match self.last_polls.lock() {
+            Ok(last_polls) => last_polls.get(channel).cloned(),
+            Err(error) => {
+                eprintln!("Cannot get last polls Lock, error: {:?}", error);
+                None
+            }
+        };
+        if last_poll.is_some() {
+            return last_poll;
+        }
+
+        match polls::get_last_poll(&self.helix_client, &self.token, &self.broadcaster_id).await {
+            Ok(Some(helix_poll)) => match Poll::try_from(helix_poll.clone()) {
+                Ok(poll) => Some(poll),
+                Err(e) => {
+                    eprintln!("Error building Poll for Helix Poll {:?}, error: {:?}.", helix_poll, e);
+                    None
+                }
+            },
+            Ok(None) => {
+                eprintln!("No Polls found for broadcaster {:?}.", self.broadcaster_id);
+                None
+            }
+            Err(e) => {
+                eprintln!(
+                    "Error getting Polls of broadcaster {:?}, error: {:?}.",
+                    self.broadcaster_id, e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PollTimeEvent {
+    channel: String,
+    kind: &'static str,
+}
+
+impl MatchableMessage for PollTimeEvent {
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn text(&self) -> &str {
+        self.kind
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Poll {
+    pub id: String,
+    pub title: String,
+    pub choices: Vec<Choice>,
+    pub state: PollState,
+    pub duration: Duration,
+    pub started_at: DateTime<Utc>,
+    pub total_votes: i64,
+    /// Most voted choices, more than one on ties.
+    pub leaders: Vec<Choice>,
+}
+
+impl Poll {
+    pub fn new(
+        id: String,
+        title: String,
+        choices: Vec<Choice>,
+        state: PollState,
+        duration: Duration,
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        let total_votes: i64 = choices.iter().map(|c| c.votes).sum();
+        let choices: Vec<Choice> = choices
+            .into_iter()
+            .map(|c| Choice {
+                percentage: if total_votes > 0 {
+                    c.votes as f64 * 100.0 / total_votes as f64
+                } else {
+                    0.0
+                },
+                ..c
+            })
+            .collect();
+        let max_votes = choices.iter().map(|c| c.votes).max().unwrap_or_default();
+        let leaders = if max_votes > 0 {
+            choices.iter().filter(|c| c.votes == max_votes).cloned().collect()
+        } else {
+            vec![]
+        };
+
+        Self {
+            id,
+            title,
+            choices,
+            state,
+            duration,
+            started_at,
+            total_votes,
+            leaders,
+        }
+    }
+}
+
+impl Dummy<Faker> for Poll {
+    fn dummy_with_rng<R: rand::Rng + ?Sized>(_: &Faker, rng: &mut R) -> Self {
+        Self::new(
+            Faker.fake_with_rng(rng),
+            Faker.fake_with_rng(rng),
+            Faker.fake_with_rng(rng),
+            Faker.fake_with_rng(rng),
+            std::time::Duration::new(Faker.fake_with_rng(rng), Faker.fake_with_rng(rng)),
+            Faker.fake_with_rng(rng),
+        )
+    }
+}
+
+impl TryFrom<HelixPoll> for Poll {
+    type Error = anyhow::Error;
+
+    fn try_from(x: HelixPoll) -> Result<Self, Self::Error> {
+        Ok(Self::new(
+            x.id.to_string(),
+            x.title.clone(),
+            x.choices.clone().into_iter().map(Choice::from).collect(),
+            PollState::try_from(&x)?,
+            Duration::from_secs(x.duration.max(0) as u64),
+            x.started_at.as_str().parse()?,
+        ))
+    }
+}
+
+impl TryFrom<&Event> for Poll {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let (x, state) = match event {
+            Event::PollBegin(x) | Event::PollProgress(x) => (x, PollState::Up),
+            Event::PollEnd(x) => {
+                let ended_at = x
+                    .ended_at
+                    .ok_or_else(|| anyhow!("Missing ended_at in ended poll {:?}.", x))?;
+                let state = match x.status.as_deref() {
+                    Some("completed") => PollState::Completed { ended_at },
+                    Some("terminated") => PollState::Terminated { ended_at },
+                    Some("archived") => PollState::Archived { ended_at },
+                    unexpected_status => bail!("Unexpected status {:?} for poll {:?}.", unexpected_status, x),
+                };
+                (x, state)
+            }
+            unexpected_event => bail!("Unexpected event {:?} for Poll.", unexpected_event),
+        };
+
+        Ok(Self::new(
+            x.id.clone(),
+            x.title.clone(),
+            x.choices.clone().into_iter().map(Choice::from).collect(),
+            state,
+            x.ends_at
+                .and_then(|ends_at| (ends_at - x.started_at).to_std().ok())
+                .unwrap_or_default(),
+            x.started_at,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Dummy)]
+pub struct Choice {
+    pub title: String,
+    pub votes: i64,
+    pub channel_points_votes: i64,
+    pub percentage: f64,
+}
+
+impl From<PollChoice> for Choice {
+    fn from(x: PollChoice) -> Self {
+        Self {
+            title: x.title,
+            votes: x.votes.unwrap_or_default(),
+            channel_points_votes: x.channel_points_votes.unwrap_or_default(),
+            percentage: 0.0,
+        }
+    }
+}
+
+impl From<PollEventChoice> for Choice {
+    fn from(x: PollEventChoice) -> Self {
+        Self {
+            title: x.title,
+            votes: x.votes.unwrap_or_default(),
+            channel_points_votes: x.channel_points_votes.unwrap_or_default(),
+            percentage: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Dummy)]
+#[serde(tag = "name")]
+pub enum PollState {
+    Up,
+    Completed { ended_at: DateTime<Utc> },
+    Terminated { ended_at: DateTime<Utc> },
+    Archived { ended_at: DateTime<Utc> },
+}
+
+impl TryFrom<&HelixPoll> for PollState {
+    type Error = anyhow::Error;
+
+    fn try_from(x: &HelixPoll) -> Result<Self, Self::Error> {
+        let ended_at = || -> anyhow::Result<DateTime<Utc>> {
+            Ok(x.ended_at
+                .as_ref()
+                .ok_or_else(|| anyhow!("Missing ended_at in {:?} poll {:?}.", x.status, x))?
+                .as_str()
+                .parse()?)
+        };
+
+        Ok(match &x.status {
+            PollStatus::Active => Self::Up,
+            PollStatus::Completed => Self::Completed { ended_at: ended_at()? },
+            PollStatus::Terminated => Self::Terminated { ended_at: ended_at()? },
+            PollStatus::Archived => Self::Archived { ended_at: ended_at()? },
+            unexpected_variant => bail!("Unexpected variant {:?} for poll {:?}.", unexpected_variant, x),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_poll_try_from_poll_end_event_works_as_expected() {
+        let event = Event::parse(
+            "channel.poll.end",
+            json!({
+                "id": "1243456",
+                "broadcaster_user_id": "1337",
+                "broadcaster_user_login": "cool_user",
+                "broadcaster_user_name": "Cool_User",
+                "title": "Aren’t shoes just really hard socks?",
+                "choices": [
+                    {"id": "123", "title": "Blue", "bits_votes": 50, "channel_points_votes": 70, "votes": 120},
+                    {"id": "124", "title": "Yellow", "bits_votes": 100, "channel_points_votes": 40, "votes": 120},
+                    {"id": "125", "title": "Green", "bits_votes": 10, "channel_points_votes": 70, "votes": 40}
+                ],
+                "bits_voting": {"is_enabled": true, "amount_per_vote": 10},
+                "channel_points_voting": {"is_enabled": true, "amount_per_vote": 10},
+                "status": "completed",
+                "started_at": "2020-07-15T17:16:03.17106713Z",
+                "ended_at": "2020-07-15T17:16:11.17106713Z"
+            }),
+        )
+        .unwrap();
+
+        let poll = Poll::try_from(&event).unwrap();
+
+        assert_eq!(280, poll.total_votes);
+        assert_eq!(
+            vec!["Blue", "Yellow"],
+            poll.leaders.iter().map(|c| c.title.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![43, 43, 14],
+            poll.choices
+                .iter()
+                .map(|c| c.percentage.round() as i64)
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(poll.state, PollState::Completed { .. }));
+    }
+
+    #[test]
+    fn test_poll_without_votes_has_no_leaders() {
+        let poll = Poll::new(
+            Faker.fake(),
+            Faker.fake(),
+            vec![
+                Choice {
+                    title: "foo".into(),
+                    votes: 0,
+                    channel_points_votes: 0,
+                    percentage: 0.0,
+                },
+                Choice {
+                    title: "bar".into(),
+                    votes: 0,
+                    channel_points_votes: 0,
+                    percentage: 0.0,
+                },
+            ],
+            PollState::Up,
+            Duration::from_secs(60),
+            Faker.fake(),
+        );
+
+        assert_eq!(0, poll.total_votes);
+        assert!(poll.leaders.is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::handlers::persistence::capture;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::quotes::persistence::Quote;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Channel quotes, e.g. `!quote add <text>`, `!quote`, `!quote <number>`, `!quote search <word>` and
+/// `!quote delete <number>`.
+///
+/// The reply pattern can capture the `action` (`add`, `search` or `delete`) and its `arg`.
+/// Without an `action` a random quote is picked or, if the `arg` is a number, the quote with that number.
+/// Only mods can delete quotes.
+pub struct Quotes<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl Quotes<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::Quotes
+    }
+}
+
+impl Quotes<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let quote_command = match QuoteCommand::try_from(&reply.captures(message)) {
+                        Ok(quote_command) => quote_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing quote command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    if matches!(quote_command, QuoteCommand::Delete { .. })
+                        && !twitch::helpers::is_from_streamer_or_mod(message)
+                    {
+                        return;
+                    }
+
+                    let template_inputs = match self.execute(message, &quote_command).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!("Error executing quote command {:?}, error: {:?}.", quote_command, error);
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn execute(&self, message: &PrivmsgMessage, quote_command: &QuoteCommand) -> anyhow::Result<TemplateInputs> {
+        let channel = message.channel_login.as_str();
+
+        let mut template_inputs = TemplateInputs {
+            action: quote_command.action(),
+            quote: None,
+            quotes: vec![],
+            deleted: false,
+        };
+
+        match quote_command {
+            QuoteCommand::Add { text } => {
+                let category = self.streams.category(channel);
+                template_inputs.quote =
+                    Some(Quote::add(channel, text, category.as_deref(), &message.sender.login, &self.db_pool).await?);
+            }
+            QuoteCommand::Random => template_inputs.quote = Quote::random(channel, &self.db_pool).await?,
+            QuoteCommand::Get { number } => template_inputs.quote = Quote::get(channel, *number, &self.db_pool).await?,
+            QuoteCommand::Search { word } => {
+                template_inputs.quotes = Quote::search(channel, word, &self.db_pool).await?;
+                template_inputs.quote = template_inputs.quotes.first().cloned();
+            }
+            QuoteCommand::Delete { number } => {
+                template_inputs.quote = Quote::get(channel, *number, &self.db_pool).await?;
+                template_inputs.deleted = Quote::delete(channel, *number, &self.db_pool).await?;
+            }
+        }
+
+        Ok(template_inputs)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum QuoteCommand {
+    Add { text: String },
+    Random,
+    Get { number: i64 },
+    Search { word: String },
+    Delete { number: i64 },
+}
+
+impl QuoteCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Add { .. } => "add",
+            Self::Random => "random",
+            Self::Get { .. } => "get",
+            Self::Search { .. } => "search",
+            Self::Delete { .. } => "delete",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for QuoteCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        let arg = || required_capture(captures, "arg");
+        let number = |arg: &str| {
+            arg.trim_start_matches('#')
+                .parse::<i64>()
+                .map_err(|e| anyhow!("Cannot parse {:?} as quote number, error: {:?}.", arg, e))
+        };
+
+        Ok(match capture(captures, "action").map(str::to_lowercase).as_deref() {
+            Some("add") => Self::Add { text: arg()?.into() },
+            Some("search") | Some("find") => Self::Search { word: arg()?.into() },
+            Some("delete") | Some("del") | Some("remove") => Self::Delete {
+                number: number(arg()?)?,
+            },
+            Some(unexpected_action) => bail!("Unexpected quote action {:?}.", unexpected_action),
+            None => match capture(captures, "arg") {
+                Some(arg) => Self::Get { number: number(arg)? },
+                None => Self::Random,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    /// The added, picked, first found or deleted quote, if any.
+    quote: Option<Quote>,
+    /// The found quotes.
+    quotes: Vec<Quote>,
+    deleted: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+    use crate::handlers::persistence::test_db_pool;
+
+    #[test]
+    fn test_quote_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            QuoteCommand::Add {
+                text: "I'm not tilted".into()
+            },
+            QuoteCommand::try_from(&test_captures(&[("action", "add"), ("arg", " I'm not tilted ")])).unwrap()
+        );
+        assert!(QuoteCommand::try_from(&test_captures(&[("action", "add")])).is_err());
+        assert_eq!(
+            QuoteCommand::Random,
+            QuoteCommand::try_from(&test_captures(&[])).unwrap()
+        );
+        assert_eq!(
+            QuoteCommand::Get { number: 42 },
+            QuoteCommand::try_from(&test_captures(&[("arg", "#42")])).unwrap()
+        );
+        assert!(QuoteCommand::try_from(&test_captures(&[("arg", "foo")])).is_err());
+        assert_eq!(
+            QuoteCommand::Search { word: "tilted".into() },
+            QuoteCommand::try_from(&test_captures(&[("action", "Search"), ("arg", "tilted")])).unwrap()
+        );
+        assert_eq!(
+            QuoteCommand::Delete { number: 42 },
+            QuoteCommand::try_from(&test_captures(&[("action", "del"), ("arg", "42")])).unwrap()
+        );
+        assert!(QuoteCommand::try_from(&test_captures(&[("action", "foo"), ("arg", "42")])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quote_numbers_are_not_reused_after_delete() {
+        let db_pool = test_db_pool().await;
+
+        assert_eq!(
+            1,
+            Quote::add("foo", "first", None, "bar", &db_pool).await.unwrap().number
+        );
+        assert_eq!(
+            2,
+            Quote::add("foo", "second", None, "bar", &db_pool).await.unwrap().number
+        );
+        assert_eq!(
+            1,
+            Quote::add("baz", "other", None, "bar", &db_pool).await.unwrap().number
+        );
+
+        assert!(Quote::delete("foo", 2, &db_pool).await.unwrap());
+        assert!(!Quote::delete("foo", 2, &db_pool).await.unwrap());
+        assert_eq!(None, Quote::get("foo", 2, &db_pool).await.unwrap());
+        assert!(Quote::search("foo", "second", &db_pool).await.unwrap().is_empty());
+
+        let quote = Quote::add("foo", "third", None, "bar", &db_pool).await.unwrap();
+        assert_eq!(3, quote.number);
+        assert_eq!(Some(quote), Quote::get("foo", 3, &db_pool).await.unwrap());
+    }
+}
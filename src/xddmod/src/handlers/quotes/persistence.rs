@@ -0,0 +1,138 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+pub const MAX_SEARCHED_QUOTES: i64 = 5;
+
+/// Quotes are numbered per channel. Deleted ones are kept to not reuse their numbers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Quote {
+    pub channel: String,
+    pub number: i64,
+    pub text: String,
+    /// Category of the stream when the quote was added.
+    pub category: Option<String>,
+    pub quoted_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Quote {
+    pub async fn add<'a>(
+        channel: &str,
+        text: &str,
+        category: Option<&str>,
+        quoted_by: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into quotes (channel, number, text, category, quoted_by)
+                values ($1, (select coalesce(max(number), 0) + 1 from quotes where channel = $1), $2, $3, $4)
+                returning
+                    channel,
+                    number,
+                    text,
+                    category,
+                    quoted_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            text,
+            category,
+            quoted_by,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn get<'a>(channel: &str, number: i64, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    number,
+                    text,
+                    category,
+                    quoted_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from quotes
+                where channel = $1 and number = $2 and deleted_at is null
+            "#,
+            channel,
+            number,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn random<'a>(channel: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    number,
+                    text,
+                    category,
+                    quoted_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from quotes
+                where channel = $1 and deleted_at is null
+                order by random()
+                limit 1
+            "#,
+            channel,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Quotes containing the word, ignoring ASCII case, oldest first.
+    pub async fn search<'a>(channel: &str, word: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    number,
+                    text,
+                    category,
+                    quoted_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from quotes
+                where channel = $1 and deleted_at is null and instr(lower(text), lower($2)) > 0
+                order by number asc
+                limit $3
+            "#,
+            channel,
+            word,
+            MAX_SEARCHED_QUOTES,
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn delete<'a>(channel: &str, number: i64, executor: impl SqliteExecutor<'a>) -> sqlx::Result<bool> {
+        sqlx::query!(
+            r#"
+                update quotes
+                set deleted_at = current_timestamp, updated_at = current_timestamp
+                where channel = $1 and number = $2 and deleted_at is null
+            "#,
+            channel,
+            number
+        )
+        .execute(executor)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use minijinja::value::Value;
+use minijinja::Environment;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::handlers::persistence::capture;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::raffles::persistence::Raffle;
+use crate::handlers::raffles::persistence::RaffleEntry;
+use crate::handlers::raffles::persistence::RaffleRestriction;
+use crate::handlers::raffles::persistence::RaffleState;
+use crate::handlers::raffles::persistence::RaffleWinner;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+pub const MAX_SUB_LUCK: i64 = 10;
+pub const MAX_WINNERS: usize = 10;
+
+/// Raffles entered by sending their keyword in chat, e.g. `!raffle open <keyword>`, `!raffle close`,
+/// `!raffle draw <winners>` and `!raffle cancel`.
+///
+/// The mods reply pattern must capture the `action` (`open`, `close`, `draw` or `cancel`).
+/// `open` also needs the `keyword` plus, optionally, the subscribers `luck` multiplier and the `restriction`
+/// (`everyone` or `subscribers`). `draw` can capture the number of `winners`, excluding the ones already drawn.
+/// The template gets the `raffle`, the drawn `winners` and the `entries_count`.
+pub struct Raffles<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+    open_raffles: Mutex<HashMap<String, Option<Raffle>>>,
+}
+
+impl<'a> Raffles<'a> {
+    pub fn new(outbox: Outbox, streams: Streams, db_pool: SqlitePool, templates_env: Environment<'a>) -> Self {
+        Self {
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            open_raffles: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn handler(&self) -> Handler {
+        Handler::Raffles
+    }
+}
+
+impl Raffles<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if let Some(raffle) = self.open_raffle(&message.channel_login).await {
+                if message.message_text.trim().eq_ignore_ascii_case(&raffle.keyword) {
+                    self.enter(&raffle, message).await;
+                    return;
+                }
+            }
+
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let raffle_command = match RaffleCommand::try_from(&reply.captures(message)) {
+                        Ok(raffle_command) => raffle_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing raffle command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(message, &raffle_command).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!(
+                                "Error executing raffle command {:?}, error: {:?}.",
+                                raffle_command, error
+                            );
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say(&message.channel_login, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn enter(&self, raffle: &Raffle, message: &PrivmsgMessage) {
+        let is_subscriber = is_subscriber(message);
+        if raffle.restriction == RaffleRestriction::Subscribers && !is_subscriber {
+            return;
+        }
+
+        let tickets = if is_subscriber { raffle.sub_luck.max(1) } else { 1 };
+        if let Err(error) = RaffleEntry::add(
+            raffle.id,
+            &message.sender.login,
+            &message.sender.name,
+            tickets,
+            &self.db_pool,
+        )
+        .await
+        {
+            eprintln!("Error entering raffle {:?}, error: {:?}.", raffle, error);
+        }
+    }
+
+    async fn execute(
+        &self,
+        message: &PrivmsgMessage,
+        raffle_command: &RaffleCommand,
+    ) -> anyhow::Result<TemplateInputs> {
+        let channel = message.channel_login.as_str();
+        let last_raffle = Raffle::last_by_channel(channel, &self.db_pool).await?;
+        let running_raffle = |states: &[RaffleState]| {
+            last_raffle
+                .clone()
+                .filter(|r| states.contains(&r.state))
+                .ok_or_else(|| anyhow!("No raffle in states {:?} for channel {:?}.", states, channel))
+        };
+
+        let mut template_inputs = TemplateInputs {
+            action: raffle_command.action(),
+            raffle: None,
+            winners: vec![],
+            entries_count: 0,
+        };
+
+        let raffle = match raffle_command {
+            RaffleCommand::Open {
+                keyword,
+                sub_luck,
+                restriction,
+            } => {
+                if let Ok(raffle) = running_raffle(&[RaffleState::Open, RaffleState::Closed]) {
+                    bail!("Raffle already running {:?}.", raffle);
+                }
+                Raffle::open(
+                    channel,
+                    keyword,
+                    *sub_luck,
+                    *restriction,
+                    &message.sender.login,
+                    &self.db_pool,
+                )
+                .await?
+            }
+            RaffleCommand::Close => {
+                let raffle = running_raffle(&[RaffleState::Open])?;
+                Raffle::set_state(raffle.id, RaffleState::Closed, &self.db_pool).await?
+            }
+            RaffleCommand::Draw { winners } => {
+                let raffle = running_raffle(&[RaffleState::Open, RaffleState::Closed, RaffleState::Drawn])?;
+                let raffle = Raffle::set_state(raffle.id, RaffleState::Drawn, &self.db_pool).await?;
+
+                let entries = RaffleEntry::all_by_raffle(raffle.id, &self.db_pool).await?;
+                let already_drawn: HashSet<String> = RaffleWinner::all_by_raffle(raffle.id, &self.db_pool)
+                    .await?
+                    .into_iter()
+                    .map(|w| w.user_login)
+                    .collect();
+                let candidates: Vec<RaffleEntry> = entries
+                    .iter()
+                    .filter(|e| !already_drawn.contains(&e.user_login))
+                    .cloned()
+                    .collect();
+
+                let drawn = draw(&candidates, *winners, &mut rand::thread_rng());
+                for entry in drawn {
+                    template_inputs
+                        .winners
+                        .push(RaffleWinner::add(raffle.id, &entry.user_login, &entry.user_name, &self.db_pool).await?);
+                }
+                template_inputs.entries_count = entries.len();
+
+                raffle
+            }
+            RaffleCommand::Cancel => {
+                let raffle = running_raffle(&[RaffleState::Open, RaffleState::Closed])?;
+                Raffle::set_state(raffle.id, RaffleState::Canceled, &self.db_pool).await?
+            }
+        };
+
+        self.cache_open_raffle(channel, Some(raffle.clone()).filter(|r| r.state == RaffleState::Open));
+        template_inputs.raffle = Some(raffle);
+
+        Ok(template_inputs)
+    }
+
+    async fn open_raffle(&self, channel: &str) -> Option<Raffle> {
+        match self.open_raffles.lock() {
+            Ok(open_raffles) => {
+                if let Some(open_raffle) = open_raffles.get(channel) {
+                    return open_raffle.clone();
+                }
+            }
+            Err(error) => {
+                eprintln!("Cannot get open raffles Lock, error: {:?}", error);
+                return None;
+            }
+        }
+
+        match Raffle::last_by_channel(channel, &self.db_pool).await {
+            Ok(raffle) => {
+                let open_raffle = raffle.filter(|r| r.state == RaffleState::Open);
+                self.cache_open_raffle(channel, open_raffle.clone());
+                open_raffle
+            }
+            Err(error) => {
+                eprintln!("Error loading raffle of channel {:?}, error: {:?}", channel, error);
+                None
+            }
+        }
+    }
+
+    fn cache_open_raffle(&self, channel: &str, open_raffle: Option<Raffle>) {
+        match self.open_raffles.lock() {
+            Ok(mut open_raffles) => {
+                open_raffles.insert(channel.into(), open_raffle);
+            }
+            Err(error) => eprintln!("Cannot get open raffles Lock, error: {:?}", error),
+        }
+    }
+}
+
+fn is_subscriber(message: &PrivmsgMessage) -> bool {
+    message
+        .badge_info
+        .iter()
+        .any(|b| b.name == "subscriber" || b.name == "founder")
+}
+
+/// Draws up to `winners` distinct entries, weighted by their tickets.
+fn draw<R: Rng + ?Sized>(entries: &[RaffleEntry], winners: usize, rng: &mut R) -> Vec<RaffleEntry> {
+    match entries.choose_multiple_weighted(rng, winners, |e| e.tickets.max(1) as f64) {
+        Ok(drawn) => drawn.cloned().collect(),
+        Err(error) => {
+            eprintln!("Error drawing raffle winners from {:?}, error: {:?}", entries, error);
+            vec![]
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RaffleCommand {
+    Open {
+        keyword: String,
+        sub_luck: i64,
+        restriction: RaffleRestriction,
+    },
+    Close,
+    Draw {
+        winners: usize,
+    },
+    Cancel,
+}
+
+impl RaffleCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Open { .. } => "open",
+            Self::Close => "close",
+            Self::Draw { .. } => "draw",
+            Self::Cancel => "cancel",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for RaffleCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "open" | "start" => {
+                let sub_luck = match capture(captures, "luck") {
+                    Some(luck) => luck.trim_end_matches(['x', 'X']).parse::<i64>()?,
+                    None => 1,
+                };
+                if !(1..=MAX_SUB_LUCK).contains(&sub_luck) {
+                    bail!("Expected luck between 1 and {}, got {:?}.", MAX_SUB_LUCK, sub_luck);
+                }
+
+                let restriction = match capture(captures, "restriction").map(str::to_lowercase).as_deref() {
+                    None | Some("everyone") | Some("all") => RaffleRestriction::Everyone,
+                    Some("subscribers") | Some("subs") | Some("sub") => RaffleRestriction::Subscribers,
+                    Some(unexpected_restriction) => {
+                        bail!("Unexpected raffle restriction {:?}.", unexpected_restriction)
+                    }
+                };
+
+                Self::Open {
+                    keyword: required_capture(captures, "keyword")?.into(),
+                    sub_luck,
+                    restriction,
+                }
+            }
+            "close" | "stop" => Self::Close,
+            "draw" | "roll" | "reroll" => {
+                let winners = match capture(captures, "winners") {
+                    Some(winners) => winners.parse::<usize>()?,
+                    None => 1,
+                };
+                if !(1..=MAX_WINNERS).contains(&winners) {
+                    bail!("Expected between 1 and {} winners, got {:?}.", MAX_WINNERS, winners);
+                }
+
+                Self::Draw { winners }
+            }
+            "cancel" => Self::Cancel,
+            unexpected_action => bail!("Unexpected raffle action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    raffle: Option<Raffle>,
+    /// The winners drawn by this command.
+    winners: Vec<RaffleWinner>,
+    entries_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_raffle_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            RaffleCommand::Open {
+                keyword: "!join".into(),
+                sub_luck: 2,
+                restriction: RaffleRestriction::Subscribers
+            },
+            RaffleCommand::try_from(&test_captures(&[
+                ("action", "open"),
+                ("keyword", "!join"),
+                ("luck", "2x"),
+                ("restriction", "Subs")
+            ]))
+            .unwrap()
+        );
+        assert_eq!(
+            RaffleCommand::Open {
+                keyword: "!join".into(),
+                sub_luck: 1,
+                restriction: RaffleRestriction::Everyone
+            },
+            RaffleCommand::try_from(&test_captures(&[("action", "open"), ("keyword", "!join")])).unwrap()
+        );
+        assert!(RaffleCommand::try_from(&test_captures(&[("action", "open")])).is_err());
+        assert!(RaffleCommand::try_from(&test_captures(&[
+            ("action", "open"),
+            ("keyword", "!join"),
+            ("luck", "42")
+        ]))
+        .is_err());
+        assert_eq!(
+            RaffleCommand::Draw { winners: 3 },
+            RaffleCommand::try_from(&test_captures(&[("action", "draw"), ("winners", "3")])).unwrap()
+        );
+        assert_eq!(
+            RaffleCommand::Draw { winners: 1 },
+            RaffleCommand::try_from(&test_captures(&[("action", "reroll")])).unwrap()
+        );
+        assert!(RaffleCommand::try_from(&test_captures(&[("action", "draw"), ("winners", "0")])).is_err());
+        assert_eq!(
+            RaffleCommand::Close,
+            RaffleCommand::try_from(&test_captures(&[("action", "close")])).unwrap()
+        );
+        assert_eq!(
+            RaffleCommand::Cancel,
+            RaffleCommand::try_from(&test_captures(&[("action", "cancel")])).unwrap()
+        );
+        assert!(RaffleCommand::try_from(&test_captures(&[("action", "foo")])).is_err());
+    }
+
+    #[test]
+    fn test_draw_works_as_expected() {
+        let entry = |user_login: &str, tickets: i64| RaffleEntry {
+            raffle_id: 42,
+            user_login: user_login.into(),
+            user_name: user_login.into(),
+            tickets,
+            created_at: Utc::now(),
+        };
+        let entries = vec![entry("foo", 1), entry("bar", 2), entry("baz", 1)];
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let drawn = draw(&entries, 2, &mut rng);
+        assert_eq!(2, drawn.len());
+        assert_ne!(drawn[0].user_login, drawn[1].user_login);
+
+        let drawn = draw(&entries, 5, &mut rng);
+        let mut logins: Vec<&str> = drawn.iter().map(|e| e.user_login.as_str()).collect();
+        logins.sort();
+        assert_eq!(vec!["bar", "baz", "foo"], logins);
+
+        assert!(draw(&[], 1, &mut rng).is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Raffle {
+    pub id: i64,
+    pub channel: String,
+    /// Chat message to send to enter the raffle.
+    pub keyword: String,
+    /// Tickets of each subscriber entry.
+    pub sub_luck: i64,
+    pub restriction: RaffleRestriction,
+    pub state: RaffleState,
+    pub opened_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+pub enum RaffleRestriction {
+    Everyone,
+    Subscribers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+pub enum RaffleState {
+    /// Collecting entries.
+    Open,
+    /// Waiting for the draw.
+    Closed,
+    /// Winners drawn, more can be drawn.
+    Drawn,
+    Canceled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RaffleEntry {
+    pub raffle_id: i64,
+    pub user_login: String,
+    pub user_name: String,
+    pub tickets: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RaffleWinner {
+    pub raffle_id: i64,
+    pub user_login: String,
+    pub user_name: String,
+    pub drawn_at: DateTime<Utc>,
+}
+
+impl Raffle {
+    pub async fn open<'a>(
+        channel: &str,
+        keyword: &str,
+        sub_luck: i64,
+        restriction: RaffleRestriction,
+        opened_by: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into raffles (channel, keyword, sub_luck, restriction, opened_by)
+                values ($1, $2, $3, $4, $5)
+                returning
+                    id,
+                    channel,
+                    keyword,
+                    sub_luck,
+                    restriction as "restriction: RaffleRestriction",
+                    state as "state: RaffleState",
+                    opened_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            keyword,
+            sub_luck,
+            restriction,
+            opened_by,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Most recent raffle of the channel, whatever its state.
+    pub async fn last_by_channel<'a>(channel: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    id,
+                    channel,
+                    keyword,
+                    sub_luck,
+                    restriction as "restriction: RaffleRestriction",
+                    state as "state: RaffleState",
+                    opened_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from raffles
+                where channel = $1
+                order by id desc
+                limit 1
+            "#,
+            channel,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn set_state<'a>(id: i64, state: RaffleState, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                update raffles
+                set state = $2, updated_at = current_timestamp
+                where id = $1
+                returning
+                    id,
+                    channel,
+                    keyword,
+                    sub_luck,
+                    restriction as "restriction: RaffleRestriction",
+                    state as "state: RaffleState",
+                    opened_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            id,
+            state,
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
+
+impl RaffleEntry {
+    /// Users can enter only once, later entries are ignored.
+    pub async fn add<'a>(
+        raffle_id: i64,
+        user_login: &str,
+        user_name: &str,
+        tickets: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<bool> {
+        sqlx::query!(
+            r#"
+                insert into raffle_entries (raffle_id, user_login, user_name, tickets)
+                values ($1, $2, $3, $4)
+                on conflict (raffle_id, user_login) do nothing
+            "#,
+            raffle_id,
+            user_login,
+            user_name,
+            tickets,
+        )
+        .execute(executor)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    pub async fn all_by_raffle<'a>(raffle_id: i64, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    raffle_id,
+                    user_login,
+                    user_name,
+                    tickets,
+                    created_at as "created_at!: DateTime<Utc>"
+                from raffle_entries
+                where raffle_id = $1
+                order by created_at asc
+            "#,
+            raffle_id,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+impl RaffleWinner {
+    pub async fn add<'a>(
+        raffle_id: i64,
+        user_login: &str,
+        user_name: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into raffle_winners (raffle_id, user_login, user_name)
+                values ($1, $2, $3)
+                returning
+                    raffle_id,
+                    user_login,
+                    user_name,
+                    drawn_at as "drawn_at!: DateTime<Utc>"
+            "#,
+            raffle_id,
+            user_login,
+            user_name,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn all_by_raffle<'a>(raffle_id: i64, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    raffle_id,
+                    user_login,
+                    user_name,
+                    drawn_at as "drawn_at!: DateTime<Utc>"
+                from raffle_winners
+                where raffle_id = $1
+                order by drawn_at asc
+            "#,
+            raffle_id,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,331 @@
+use anyhow::anyhow;
+use anyhow::bail;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::helix::points::CustomRewardRedemptionStatus;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+
+use crate::apis::twitch::eventsub::Event;
+use crate::apis::twitch::eventsub::RedemptionEvent;
+use crate::apis::twitch::rewards;
+use crate::handlers::counters::persistence::Counter;
+use crate::handlers::persistence::Channel;
+use crate::handlers::quotes::persistence::Quote;
+use crate::handlers::redemptions::persistence::Reward;
+use crate::handlers::redemptions::persistence::RewardAction;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+use crate::templates_env;
+
+/// Runs the action and renders the template configured for the redeemed custom reward.
+///
+/// Rewards with `settle` mark the redemption fulfilled when the action succeeds and cancel it, refunding the points,
+/// when it fails.
+pub struct Redemptions<'a> {
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl Redemptions<'_> {
+    pub async fn handle_event(&mut self, event: &Event) {
+        let Event::RedemptionAdd(redemption) = event else {
+            return;
+        };
+
+        let reward = match Reward::enabled_by_id(&redemption.reward.id, &self.db_pool).await {
+            Ok(Some(reward)) => reward,
+            Ok(None) => return,
+            Err(error) => {
+                eprintln!("Error loading reward {:?}, error: {:?}.", redemption.reward, error);
+                return;
+            }
+        };
+
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for redemptions");
+            if let Err(error) = self.token.refresh_token(self.helix_client.get_client()).await {
+                eprintln!("Error refreshing token for redemptions, error: {:?}", error);
+            }
+        }
+
+        let template_inputs = self.perform(&reward, redemption).await;
+
+        if let Some(status) = settlement_status(&reward, redemption, &template_inputs) {
+            if let Err(error) = rewards::update_redemption_status(
+                &self.helix_client,
+                &self.token,
+                &self.broadcaster_id,
+                &redemption.reward.id,
+                &redemption.id,
+                status,
+            )
+            .await
+            {
+                eprintln!("Error settling redemption {:?}, error: {:?}.", redemption, error);
+            }
+        }
+
+        let template_inputs = match template_inputs {
+            Ok(template_inputs) => template_inputs,
+            Err(error) => {
+                eprintln!("Error performing reward action {:?}, error: {:?}.", reward, error);
+                return;
+            }
+        };
+
+        let Some(template) = &reward.template else {
+            return;
+        };
+        let channel = Channel::by_name_or_default(&redemption.broadcaster_user_login, &self.db_pool).await;
+        match templates_env::render_template(
+            &self.templates_env,
+            template,
+            &channel,
+            &self.streams,
+            &self.db_pool,
+            Some(&template_inputs),
+        )
+        .await
+        {
+            Ok(rendered_reward) if rendered_reward.is_empty() => {
+                eprintln!("Rendered reward template empty: {:?}.", reward)
+            }
+            Ok(rendered_reward) => self.outbox.say(&redemption.broadcaster_user_login, rendered_reward),
+            Err(e) => eprintln!("Error rendering reward template, error: {:?}, {:?}.", reward, e),
+        }
+    }
+
+    async fn perform(&self, reward: &Reward, redemption: &RedemptionEvent) -> anyhow::Result<TemplateInputs> {
+        let mut template_inputs = TemplateInputs::from(redemption);
+
+        match RedemptionAction::new(reward, redemption)? {
+            Some(RedemptionAction::IncrCounter { name }) => {
+                template_inputs.counter =
+                    Some(Counter::incr(&redemption.broadcaster_user_login, name, 1, &self.db_pool).await?);
+            }
+            Some(RedemptionAction::TimeoutSelf { duration_secs }) => {
+                self.helix_client
+                    .ban_user(
+                        &UserId::from(redemption.user_id.clone()),
+                        &format!("Redeemed {}", redemption.reward.title),
+                        Some(duration_secs),
+                        &self.broadcaster_id,
+                        &self.token.user_id,
+                        &self.token,
+                    )
+                    .await?;
+                template_inputs.timeout_secs = Some(duration_secs);
+            }
+            Some(RedemptionAction::Quote { text }) => {
+                let channel = redemption.broadcaster_user_login.as_str();
+                let category = self.streams.category(channel);
+                template_inputs.quote = Some(
+                    Quote::add(
+                        channel,
+                        text,
+                        category.as_deref(),
+                        &redemption.user_login,
+                        &self.db_pool,
+                    )
+                    .await?,
+                );
+            }
+            None => {}
+        }
+
+        Ok(template_inputs)
+    }
+}
+
+/// Action of the redeemed [`Reward`] with its arguments.
+#[derive(Debug, Clone, PartialEq)]
+enum RedemptionAction<'a> {
+    IncrCounter { name: &'a str },
+    TimeoutSelf { duration_secs: u32 },
+    Quote { text: &'a str },
+}
+
+impl<'a> RedemptionAction<'a> {
+    fn new(reward: &'a Reward, redemption: &'a RedemptionEvent) -> anyhow::Result<Option<Self>> {
+        Ok(match reward.action {
+            Some(RewardAction::IncrCounter) => Some(Self::IncrCounter {
+                name: action_arg(reward)?,
+            }),
+            Some(RewardAction::TimeoutSelf) => Some(Self::TimeoutSelf {
+                duration_secs: action_arg(reward)?.trim().parse()?,
+            }),
+            Some(RewardAction::Quote) => {
+                let text = redemption.user_input.trim();
+                if text.is_empty() {
+                    bail!("Missing quote in redemption {:?}.", redemption);
+                }
+                Some(Self::Quote { text })
+            }
+            None => None,
+        })
+    }
+}
+
+/// Redemptions of rewards skipping the requests queue are already fulfilled, the others are fulfilled if the action
+/// succeeded and canceled, refunding the points, otherwise.
+fn settlement_status<T>(
+    reward: &Reward,
+    redemption: &RedemptionEvent,
+    action_result: &anyhow::Result<T>,
+) -> Option<CustomRewardRedemptionStatus> {
+    if !reward.settle || redemption.status != "unfulfilled" {
+        return None;
+    }
+
+    Some(match action_result {
+        Ok(_) => CustomRewardRedemptionStatus::Fulfilled,
+        Err(_) => CustomRewardRedemptionStatus::Canceled,
+    })
+}
+
+fn action_arg(reward: &Reward) -> anyhow::Result<&str> {
+    reward
+        .action_arg
+        .as_deref()
+        .ok_or_else(|| anyhow!("Missing action_arg for reward {:?}.", reward))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInputs {
+    pub user: String,
+    pub user_input: String,
+    pub reward: String,
+    pub cost: i64,
+    pub counter: Option<Counter>,
+    pub timeout_secs: Option<u32>,
+    pub quote: Option<Quote>,
+}
+
+impl From<&RedemptionEvent> for TemplateInputs {
+    fn from(x: &RedemptionEvent) -> Self {
+        Self {
+            user: x.user_name.clone(),
+            user_input: x.user_input.clone(),
+            reward: x.reward.title.clone(),
+            cost: x.reward.cost,
+            counter: None,
+            timeout_secs: None,
+            quote: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::apis::twitch::eventsub::RedemptionEventReward;
+
+    #[test]
+    fn test_action_arg_works_as_expected() {
+        assert_eq!(
+            "60",
+            action_arg(&reward(Some(RewardAction::TimeoutSelf), Some("60"))).unwrap()
+        );
+        assert!(action_arg(&reward(Some(RewardAction::TimeoutSelf), None)).is_err());
+    }
+
+    #[test]
+    fn test_redemption_action_new_works_as_expected() {
+        let unfulfilled = redemption("unfulfilled", " I'm not tilted ");
+
+        assert_eq!(
+            Some(RedemptionAction::IncrCounter { name: "deaths" }),
+            RedemptionAction::new(&reward(Some(RewardAction::IncrCounter), Some("deaths")), &unfulfilled).unwrap()
+        );
+        assert!(RedemptionAction::new(&reward(Some(RewardAction::IncrCounter), None), &unfulfilled).is_err());
+        assert_eq!(
+            Some(RedemptionAction::TimeoutSelf { duration_secs: 60 }),
+            RedemptionAction::new(&reward(Some(RewardAction::TimeoutSelf), Some(" 60 ")), &unfulfilled).unwrap()
+        );
+        assert!(RedemptionAction::new(&reward(Some(RewardAction::TimeoutSelf), Some("foo")), &unfulfilled).is_err());
+        assert_eq!(
+            Some(RedemptionAction::Quote { text: "I'm not tilted" }),
+            RedemptionAction::new(&reward(Some(RewardAction::Quote), None), &unfulfilled).unwrap()
+        );
+        assert!(RedemptionAction::new(
+            &reward(Some(RewardAction::Quote), None),
+            &redemption("unfulfilled", " ")
+        )
+        .is_err());
+        assert_eq!(None, RedemptionAction::new(&reward(None, None), &unfulfilled).unwrap());
+    }
+
+    #[test]
+    fn test_settlement_status_works_as_expected() {
+        let reward = reward(Some(RewardAction::IncrCounter), Some("deaths"));
+        let unfulfilled = redemption("unfulfilled", "");
+
+        assert_eq!(
+            Some(CustomRewardRedemptionStatus::Fulfilled),
+            settlement_status(&reward, &unfulfilled, &Ok(()))
+        );
+        assert_eq!(
+            Some(CustomRewardRedemptionStatus::Canceled),
+            settlement_status::<()>(&reward, &unfulfilled, &Err(anyhow!("foo")))
+        );
+        assert_eq!(None, settlement_status(&reward, &redemption("fulfilled", ""), &Ok(())));
+        assert_eq!(
+            None,
+            settlement_status::<()>(
+                &Reward {
+                    settle: false,
+                    ..reward
+                },
+                &unfulfilled,
+                &Err(anyhow!("foo"))
+            )
+        );
+    }
+
+    fn reward(action: Option<RewardAction>, action_arg: Option<&str>) -> Reward {
+        Reward {
+            id: "42".into(),
+            channel: "foo".into(),
+            action,
+            action_arg: action_arg.map(String::from),
+            template: None,
+            settle: true,
+            enabled: true,
+            created_by: "bar".into(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn redemption(status: &str, user_input: &str) -> RedemptionEvent {
+        RedemptionEvent {
+            id: "1".into(),
+            broadcaster_user_id: "2".into(),
+            broadcaster_user_login: "foo".into(),
+            user_id: "3".into(),
+            user_login: "baz".into(),
+            user_name: "Baz".into(),
+            user_input: user_input.into(),
+            status: status.into(),
+            reward: RedemptionEventReward {
+                id: "42".into(),
+                title: "Quote".into(),
+                cost: 100,
+                prompt: "".into(),
+            },
+            redeemed_at: Utc::now(),
+        }
+    }
+}
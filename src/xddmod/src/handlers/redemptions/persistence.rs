@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+/// Behaviour of a custom channel point reward.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reward {
+    pub id: String,
+    pub channel: String,
+    pub action: Option<RewardAction>,
+    pub action_arg: Option<String>,
+    pub template: Option<String>,
+    /// Whether to mark the redemption fulfilled, or canceled if the action fails.
+    pub settle: bool,
+    pub enabled: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+pub enum RewardAction {
+    /// Increments the counter named `action_arg`.
+    IncrCounter,
+    /// Times out the redeemer for `action_arg` seconds.
+    TimeoutSelf,
+    /// Adds the redeemer input to the channel quotes.
+    Quote,
+}
+
+impl Reward {
+    pub async fn enabled_by_id<'a>(id: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    id as "id!",
+                    channel,
+                    action as "action: RewardAction",
+                    action_arg,
+                    template,
+                    settle,
+                    enabled,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from rewards
+                where id = $1 and enabled = 1
+            "#,
+            id,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::bail;
+use chrono::Utc;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::reminders::persistence::ScheduledMessage;
+use crate::handlers::reminders::persistence::ScheduledMessageKind;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+use crate::templates_env;
+
+pub const TICK: Duration = Duration::from_secs(10);
+pub const MAX_DELAY: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Messages scheduled from chat, e.g. `!remindme <duration> <text>` and the mod only `!announce-in <duration> <text>`.
+///
+/// The reply pattern must capture the `action` (`remindme` or `announce`), the `duration` (e.g. `1h30m`, see
+/// [`templates_env::parse_duration`]) and the `text`.
+/// The template gets the scheduled message plus the humanized `due_in`.
+/// Reminders are posted mentioning the requester, announcements as they are.
+pub struct Reminders<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl Reminders<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::Reminders
+    }
+}
+
+impl Reminders<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let schedule_command = match ScheduleCommand::try_from(&reply.captures(message)) {
+                        Ok(schedule_command) => schedule_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing schedule command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    if schedule_command.kind == ScheduledMessageKind::Announcement
+                        && !twitch::helpers::is_from_streamer_or_mod(message)
+                    {
+                        return;
+                    }
+
+                    let Some(due_at) = chrono::Duration::from_std(schedule_command.delay)
+                        .ok()
+                        .and_then(|delay| Utc::now().checked_add_signed(delay))
+                    else {
+                        eprintln!("Delay out of range for schedule command {:?}.", schedule_command);
+                        return;
+                    };
+                    let scheduled_message = match ScheduledMessage::schedule(
+                        &message.channel_login,
+                        schedule_command.kind,
+                        &schedule_command.text,
+                        &message.sender.login,
+                        due_at,
+                        &self.db_pool,
+                    )
+                    .await
+                    {
+                        Ok(scheduled_message) => scheduled_message,
+                        Err(error) => {
+                            eprintln!("Error scheduling message {:?}, error: {:?}.", schedule_command, error);
+                            return;
+                        }
+                    };
+
+                    let template_inputs = TemplateInputs {
+                        due_in: templates_env::humanize_duration(schedule_command.delay),
+                        scheduled_message,
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            self.post_due_messages().await;
+        }
+    }
+
+    async fn post_due_messages(&self) {
+        let scheduled_messages = match ScheduledMessage::all_due(Utc::now(), &self.db_pool).await {
+            Ok(scheduled_messages) => scheduled_messages,
+            Err(error) => {
+                eprintln!("Error loading due scheduled messages, error: {:?}", error);
+                return;
+            }
+        };
+
+        for scheduled_message in scheduled_messages {
+            // Marking first to avoid posting twice if the DB is unavailable afterwards.
+            match ScheduledMessage::mark_sent(scheduled_message.id, &self.db_pool).await {
+                Ok(true) => self.outbox.say(&scheduled_message.channel, text(&scheduled_message)),
+                Ok(false) => {}
+                Err(error) => eprintln!(
+                    "Error marking scheduled message as sent {:?}, error: {:?}",
+                    scheduled_message, error
+                ),
+            }
+        }
+    }
+}
+
+fn text(scheduled_message: &ScheduledMessage) -> String {
+    match scheduled_message.kind {
+        ScheduledMessageKind::Reminder => format!("@{} {}", scheduled_message.requested_by, scheduled_message.text),
+        ScheduledMessageKind::Announcement => scheduled_message.text.clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleCommand {
+    pub kind: ScheduledMessageKind,
+    pub delay: Duration,
+    pub text: String,
+}
+
+impl TryFrom<&HashMap<String, String>> for ScheduleCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        let kind = match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "remindme" | "remind" | "reminder" => ScheduledMessageKind::Reminder,
+            "announce" | "announce-in" => ScheduledMessageKind::Announcement,
+            unexpected_action => bail!("Unexpected schedule action {:?}.", unexpected_action),
+        };
+
+        let delay = templates_env::parse_duration(required_capture(captures, "duration")?)?;
+        if delay.is_zero() || delay > MAX_DELAY {
+            bail!("Expected delay up to {:?}, got {:?}.", MAX_DELAY, delay);
+        }
+
+        Ok(Self {
+            kind,
+            delay,
+            text: required_capture(captures, "text")?.into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TemplateInputs {
+    scheduled_message: ScheduledMessage,
+    due_in: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_schedule_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            ScheduleCommand {
+                kind: ScheduledMessageKind::Reminder,
+                delay: Duration::from_secs(90 * 60),
+                text: "drink water".into()
+            },
+            ScheduleCommand::try_from(&test_captures(&[
+                ("action", "remindme"),
+                ("duration", "1h30m"),
+                ("text", " drink water ")
+            ]))
+            .unwrap()
+        );
+        assert_eq!(
+            ScheduleCommand {
+                kind: ScheduledMessageKind::Announcement,
+                delay: Duration::from_secs(10 * 60),
+                text: "Giveaway!".into()
+            },
+            ScheduleCommand::try_from(&test_captures(&[
+                ("action", "announce-in"),
+                ("duration", "10"),
+                ("text", "Giveaway!")
+            ]))
+            .unwrap()
+        );
+        assert!(ScheduleCommand::try_from(&test_captures(&[
+            ("action", "remindme"),
+            ("duration", "31d"),
+            ("text", "foo")
+        ]))
+        .is_err());
+        assert!(ScheduleCommand::try_from(&test_captures(&[
+            ("action", "remindme"),
+            ("duration", "0s"),
+            ("text", "foo")
+        ]))
+        .is_err());
+        assert!(ScheduleCommand::try_from(&test_captures(&[("action", "remindme"), ("duration", "1h")])).is_err());
+        assert!(ScheduleCommand::try_from(&test_captures(&[
+            ("action", "foo"),
+            ("duration", "1h"),
+            ("text", "foo")
+        ]))
+        .is_err());
+    }
+}
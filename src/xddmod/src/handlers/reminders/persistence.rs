@@ -0,0 +1,103 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+/// Message to post in the channel once due, surviving restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    pub id: i64,
+    pub channel: String,
+    pub kind: ScheduledMessageKind,
+    pub text: String,
+    pub requested_by: String,
+    pub due_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+pub enum ScheduledMessageKind {
+    /// Posted mentioning the requester.
+    Reminder,
+    /// Posted as is.
+    Announcement,
+}
+
+impl ScheduledMessage {
+    pub async fn schedule<'a>(
+        channel: &str,
+        kind: ScheduledMessageKind,
+        text: &str,
+        requested_by: &str,
+        due_at: DateTime<Utc>,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into scheduled_messages (channel, kind, text, requested_by, due_at)
+                values ($1, $2, $3, $4, $5)
+                returning
+                    id,
+                    channel,
+                    kind as "kind: ScheduledMessageKind",
+                    text,
+                    requested_by,
+                    due_at as "due_at!: DateTime<Utc>",
+                    sent_at as "sent_at: DateTime<Utc>",
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            kind,
+            text,
+            requested_by,
+            due_at,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Not yet sent messages due by `now`, oldest first.
+    pub async fn all_due<'a>(now: DateTime<Utc>, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    id,
+                    channel,
+                    kind as "kind: ScheduledMessageKind",
+                    text,
+                    requested_by,
+                    due_at as "due_at!: DateTime<Utc>",
+                    sent_at as "sent_at: DateTime<Utc>",
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from scheduled_messages
+                where sent_at is null and due_at <= $1
+                order by due_at asc
+            "#,
+            now,
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn mark_sent<'a>(id: i64, executor: impl SqliteExecutor<'a>) -> sqlx::Result<bool> {
+        sqlx::query!(
+            r#"
+                update scheduled_messages
+                set sent_at = current_timestamp, updated_at = current_timestamp
+                where id = $1 and sent_at is null
+            "#,
+            id
+        )
+        .execute(executor)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+}
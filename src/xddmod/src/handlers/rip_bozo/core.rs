@@ -110,6 +110,11 @@ impl<'a> Mention<'a> {
             _ => None,
         }
     }
+
+    /// The mentioned login, without the `@`.
+    pub fn login(&self) -> &'a str {
+        self.login
+    }
 }
 
 #[derive(Debug)]
@@ -125,6 +130,15 @@ impl<'a> Mentions<'a> {
     }
 }
 
+/// The first mentioned user or the sender.
+pub fn target_login(message: &PrivmsgMessage) -> &str {
+    Mentions::new(&message.message_text)
+        .as_inner()
+        .first()
+        .map(|mention| mention.login())
+        .unwrap_or(&message.sender.login)
+}
+
 #[allow(dead_code)]
 struct TextStats<'a> {
     graphemes: Vec<&'a str>,
@@ -208,6 +222,12 @@ impl<'a> TextStats<'a> {
 
 #[cfg(test)]
 mod tests {
+    use fake::Fake;
+    use fake::Faker;
+    use twitch_irc::message::IRCMessage;
+    use twitch_irc::message::IRCTags;
+    use twitch_irc::message::TwitchUserBasics;
+
     use super::*;
 
     #[test]
@@ -394,4 +414,38 @@ mod tests {
         )
         .should_be_deleted());
     }
+
+    #[test]
+    fn test_target_login_works_as_expected() {
+        assert_eq!("sender", target_login(&privmsg("hi")));
+        assert_eq!("foo", target_login(&privmsg("hi @foo @bar")));
+    }
+
+    fn privmsg(message_text: &str) -> PrivmsgMessage {
+        PrivmsgMessage {
+            channel_login: Faker.fake(),
+            channel_id: Faker.fake(),
+            message_text: message_text.into(),
+            reply_parent: None,
+            is_action: false,
+            sender: TwitchUserBasics {
+                id: Faker.fake(),
+                login: "sender".into(),
+                name: "Sender".into(),
+            },
+            badge_info: vec![],
+            badges: vec![],
+            bits: Faker.fake(),
+            name_color: None,
+            emotes: vec![],
+            message_id: Faker.fake(),
+            server_timestamp: Faker.fake(),
+            source: IRCMessage {
+                tags: IRCTags::new(),
+                prefix: None,
+                command: Faker.fake(),
+                params: Faker.fake(),
+            },
+        }
+    }
 }
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::apis::twitch::channels;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::rip_bozo::core::Mentions;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Twitch allows a shoutout every 2 minutes...
+pub const GLOBAL_COOLDOWN_SECS: i64 = 2 * 60;
+/// ...and a shoutout to the same channel every 60 minutes.
+pub const TARGET_COOLDOWN_SECS: i64 = 60 * 60;
+
+/// Mod command to shout out the first mentioned user, e.g. `!so @user`.
+///
+/// The official Twitch shoutout is sent only while live and outside of the Twitch cooldowns, the templated message is
+/// posted anyway with the target `user`, `login`, last `category` and `title` plus whether the `official` shoutout was
+/// sent.
+///
+/// Requires the broadcaster token with the `moderator:manage:shoutouts` scope.
+pub struct Shoutout<'a> {
+    token: UserToken,
+    broadcaster_id: UserId,
+    helix_client: HelixClient<'a, reqwest::Client>,
+    outbox: Outbox,
+    streams: Streams,
+    db_pool: SqlitePool,
+    templates_env: Environment<'a>,
+    cooldowns: Cooldowns,
+}
+
+impl<'a> Shoutout<'a> {
+    pub fn new(
+        token: UserToken,
+        broadcaster_id: UserId,
+        helix_client: HelixClient<'a, reqwest::Client>,
+        outbox: Outbox,
+        streams: Streams,
+        db_pool: SqlitePool,
+        templates_env: Environment<'a>,
+    ) -> Self {
+        Self {
+            token,
+            broadcaster_id,
+            helix_client,
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            cooldowns: Cooldowns::default(),
+        }
+    }
+
+    pub fn handler(&self) -> Handler {
+        Handler::Shoutout
+    }
+}
+
+impl Shoutout<'_> {
+    pub async fn handle(&mut self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let mentions = Mentions::new(&message.message_text);
+                    let Some(mention) = mentions.as_inner().first() else {
+                        eprintln!("No user to shout out in message: {:?}.", message.message_text);
+                        return;
+                    };
+
+                    let template_inputs = match self.shoutout(mention.login(), is_live.unwrap_or(false)).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!("Error shouting out {:?}, error: {:?}.", mention, error);
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say(&message.channel_login, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn shoutout(&mut self, login: &str, is_live: bool) -> anyhow::Result<TemplateInputs> {
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for shoutout");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        let user = self
+            .helix_client
+            .get_user_from_login(login, &self.token)
+            .await?
+            .ok_or_else(|| anyhow!("No user found with login {:?}.", login))?;
+        let channel_information = channels::get_channel_information(&self.helix_client, &self.token, &user.id).await?;
+
+        let now = Utc::now();
+        let mut official = false;
+        if is_live && self.cooldowns.allow(user.login.as_str(), now) {
+            match channels::send_shoutout(&self.helix_client, &self.token, &self.broadcaster_id, &user.id).await {
+                Ok(()) => {
+                    self.cooldowns.track(user.login.as_str(), now);
+                    official = true;
+                }
+                Err(error) => eprintln!("Error sending shoutout to {:?}, error: {:?}.", user.login, error),
+            }
+        }
+
+        Ok(TemplateInputs {
+            user: user.display_name.to_string(),
+            login: user.login.to_string(),
+            category: channel_information.as_ref().map(|c| c.game_name.to_string()),
+            title: channel_information.map(|c| c.title),
+            official,
+        })
+    }
+}
+
+/// Shoutouts sent by the bot, to avoid hitting the Twitch cooldowns.
+#[derive(Debug, Default)]
+struct Cooldowns {
+    last_at: Option<DateTime<Utc>>,
+    last_at_by_login: HashMap<String, DateTime<Utc>>,
+}
+
+impl Cooldowns {
+    fn allow(&self, login: &str, now: DateTime<Utc>) -> bool {
+        let elapsed = |at: Option<&DateTime<Utc>>, secs: i64| at.is_none_or(|at| now - *at >= Duration::seconds(secs));
+
+        elapsed(self.last_at.as_ref(), GLOBAL_COOLDOWN_SECS)
+            && elapsed(self.last_at_by_login.get(login), TARGET_COOLDOWN_SECS)
+    }
+
+    fn track(&mut self, login: &str, now: DateTime<Utc>) {
+        self.last_at = Some(now);
+        self.last_at_by_login.insert(login.into(), now);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct TemplateInputs {
+    user: String,
+    login: String,
+    /// Last played category, empty if the user never streamed.
+    category: Option<String>,
+    title: Option<String>,
+    official: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_cooldowns_allow_works_as_expected() {
+        let now = Utc.with_ymd_and_hms(2023, 4, 16, 20, 0, 0).unwrap();
+        let mut cooldowns = Cooldowns::default();
+
+        assert!(cooldowns.allow("foo", now));
+
+        cooldowns.track("foo", now);
+        assert!(!cooldowns.allow("bar", now + Duration::seconds(GLOBAL_COOLDOWN_SECS - 1)));
+        assert!(cooldowns.allow("bar", now + Duration::seconds(GLOBAL_COOLDOWN_SECS)));
+        assert!(!cooldowns.allow("foo", now + Duration::seconds(GLOBAL_COOLDOWN_SECS)));
+        assert!(cooldowns.allow("foo", now + Duration::seconds(TARGET_COOLDOWN_SECS)));
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::bail;
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserId;
+use twitch_api::HelixClient;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::op_gg;
+use crate::apis::op_gg::spectate::InGame;
+use crate::apis::op_gg::spectate::SpectateStatus;
+use crate::apis::twitch;
+use crate::apis::twitch::clips;
+use crate::apis::twitch::markers;
+use crate::handlers::persistence::capture;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::stream_markers::persistence::AutoMarker;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+pub const POLLING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Mod commands to mark the live stream or clip it, e.g. `!mark <description>` and `!clip`.
+///
+/// The reply pattern must capture the `action` (`mark` or `clip`) and, optionally, the marker `description`.
+/// The template gets the marker `description` and `position_secs` or the `clip_url`.
+///
+/// Requires the broadcaster token with the `channel:manage:broadcast` and `clips:edit` scopes.
+pub struct StreamMarkers<'a> {
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl StreamMarkers<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::StreamMarkers
+    }
+}
+
+impl StreamMarkers<'_> {
+    pub async fn handle(&mut self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let stream_command = match StreamCommand::try_from(&reply.captures(message)) {
+                        Ok(stream_command) => stream_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing stream command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(&stream_command).await {
+                        Ok(template_inputs) => template_inputs,
+                        Err(error) => {
+                            eprintln!(
+                                "Error executing stream command {:?}, error: {:?}.",
+                                stream_command, error
+                            );
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    async fn execute(&mut self, stream_command: &StreamCommand) -> anyhow::Result<TemplateInputs> {
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for stream commands");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        match stream_command {
+            StreamCommand::Mark { description } => {
+                let marker = markers::create_stream_marker(
+                    &self.helix_client,
+                    &self.token,
+                    &self.broadcaster_id,
+                    description.as_deref().unwrap_or_default(),
+                )
+                .await?;
+
+                Ok(TemplateInputs {
+                    action: stream_command.action(),
+                    description: Some(marker.description),
+                    position_secs: Some(marker.position_seconds),
+                    clip_url: None,
+                })
+            }
+            StreamCommand::Clip => {
+                let clip = clips::create_clip(&self.helix_client, &self.token, &self.broadcaster_id).await?;
+
+                Ok(TemplateInputs {
+                    action: stream_command.action(),
+                    description: None,
+                    position_secs: None,
+                    clip_url: Some(clips::clip_url(&clip)),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum StreamCommand {
+    Mark { description: Option<String> },
+    Clip,
+}
+
+impl StreamCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Mark { .. } => "mark",
+            Self::Clip => "clip",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for StreamCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "mark" | "marker" => Self::Mark {
+                description: capture(captures, "description").map(String::from),
+            },
+            "clip" => Self::Clip,
+            unexpected_action => bail!("Unexpected stream action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: &'static str,
+    description: Option<String>,
+    position_secs: Option<i64>,
+    clip_url: Option<String>,
+}
+
+/// Opt-in stream markers at the start and end of the League games of the summoner configured for the channel, to find
+/// them in the VOD.
+pub struct AutoMarkers<'a> {
+    pub channel: String,
+    pub token: UserToken,
+    pub broadcaster_id: UserId,
+    pub helix_client: HelixClient<'a, reqwest::Client>,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    tracked_game_id: Option<String>,
+}
+
+impl<'a> AutoMarkers<'a> {
+    pub fn new(
+        channel: String,
+        token: UserToken,
+        broadcaster_id: UserId,
+        helix_client: HelixClient<'a, reqwest::Client>,
+        streams: Streams,
+        db_pool: SqlitePool,
+    ) -> Self {
+        Self {
+            channel,
+            token,
+            broadcaster_id,
+            helix_client,
+            streams,
+            db_pool,
+            tracked_game_id: None,
+        }
+    }
+}
+
+impl AutoMarkers<'_> {
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(POLLING_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.tick().await {
+                eprintln!(
+                    "Error managing auto markers for channel {:?}, error: {:?}",
+                    self.channel, error
+                );
+            }
+        }
+    }
+
+    async fn tick(&mut self) -> anyhow::Result<()> {
+        let Some(auto_marker) = AutoMarker::enabled_by_channel(&self.channel, &self.db_pool).await? else {
+            self.tracked_game_id = None;
+            return Ok(());
+        };
+
+        // Markers can only be created while live.
+        if self.streams.is_live(&self.channel) != Some(true) {
+            self.tracked_game_id = None;
+            return Ok(());
+        }
+
+        if self.token.is_elapsed() {
+            eprintln!("Refreshing token for auto markers");
+            self.token.refresh_token(self.helix_client.get_client()).await?;
+        }
+
+        let region = auto_marker.region()?;
+        let summoner = op_gg::summoners::get_summoner(region, &auto_marker.summoner_name).await?;
+        let spectate_status = op_gg::spectate::get_spectate_status(region, &summoner.common.summoner_id).await?;
+        let game = match &spectate_status {
+            SpectateStatus::InGame(InGame { data: game }) => Some(game),
+            SpectateStatus::NotInGame(_) => None,
+        };
+
+        let transitions = GameTransition::between(self.tracked_game_id.as_deref(), game.map(|g| g.game_id.as_str()));
+        // Tracking each created marker to not repeat it if the next one fails.
+        for transition in transitions {
+            let (description, tracked_game_id) = match transition {
+                GameTransition::Start => (
+                    format!(
+                        "Game start ({})",
+                        game.map(|g| g.queue_info.game_type.as_str()).unwrap_or_default()
+                    ),
+                    game.map(|g| g.game_id.clone()),
+                ),
+                GameTransition::End => ("Game end".into(), None),
+            };
+            markers::create_stream_marker(&self.helix_client, &self.token, &self.broadcaster_id, &description).await?;
+            self.tracked_game_id = tracked_game_id;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameTransition {
+    Start,
+    End,
+}
+
+impl GameTransition {
+    /// Transitions from the tracked game to the one currently played, if any.
+    pub fn between(tracked_game_id: Option<&str>, game_id: Option<&str>) -> Vec<Self> {
+        match (tracked_game_id, game_id) {
+            (None, None) => vec![],
+            (None, Some(_)) => vec![Self::Start],
+            (Some(_), None) => vec![Self::End],
+            (Some(tracked_game_id), Some(game_id)) if tracked_game_id == game_id => vec![],
+            (Some(_), Some(_)) => vec![Self::End, Self::Start],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_stream_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            StreamCommand::Mark {
+                description: Some("Pentakill".into())
+            },
+            StreamCommand::try_from(&test_captures(&[("action", "mark"), ("description", " Pentakill ")])).unwrap()
+        );
+        assert_eq!(
+            StreamCommand::Mark { description: None },
+            StreamCommand::try_from(&test_captures(&[("action", "Mark"), ("description", "")])).unwrap()
+        );
+        assert_eq!(
+            StreamCommand::Clip,
+            StreamCommand::try_from(&test_captures(&[("action", "clip")])).unwrap()
+        );
+        assert!(StreamCommand::try_from(&test_captures(&[("action", "foo")])).is_err());
+        assert!(StreamCommand::try_from(&test_captures(&[])).is_err());
+    }
+
+    #[test]
+    fn test_game_transition_between_works_as_expected() {
+        assert_eq!(Vec::<GameTransition>::new(), GameTransition::between(None, None));
+        assert_eq!(vec![GameTransition::Start], GameTransition::between(None, Some("42")));
+        assert_eq!(vec![GameTransition::End], GameTransition::between(Some("42"), None));
+        assert_eq!(
+            Vec::<GameTransition>::new(),
+            GameTransition::between(Some("42"), Some("42"))
+        );
+        assert_eq!(
+            vec![GameTransition::End, GameTransition::Start],
+            GameTransition::between(Some("42"), Some("43"))
+        );
+    }
+}
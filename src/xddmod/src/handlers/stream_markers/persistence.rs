@@ -0,0 +1,49 @@
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+use crate::apis::op_gg::Region;
+
+/// Summoner whose League games are marked in the stream of the channel.
+#[derive(Debug, Clone)]
+pub struct AutoMarker {
+    pub channel: String,
+    pub region: String,
+    pub summoner_name: String,
+    pub enabled: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AutoMarker {
+    pub fn region(&self) -> anyhow::Result<Region> {
+        Ok(serde_json::from_value(serde_json::Value::String(
+            self.region.to_lowercase(),
+        ))?)
+    }
+
+    pub async fn enabled_by_channel<'a>(
+        channel: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    region,
+                    summoner_name,
+                    enabled,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from auto_markers
+                where channel = $1 and enabled = 1
+            "#,
+            channel,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+}
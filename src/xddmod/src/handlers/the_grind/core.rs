@@ -11,13 +11,16 @@ use crate::apis::op_gg;
 use crate::apis::op_gg::summoners::LpHistory;
 use crate::apis::op_gg::summoners::SummonerJson;
 use crate::apis::op_gg::Region;
-use crate::auth::IRCClient;
+use crate::handlers::persistence::Channel;
 use crate::handlers::persistence::Handler;
 use crate::handlers::persistence::Reply;
+use crate::outbox::Outbox;
 use crate::poor_man_throttling;
+use crate::streams::Streams;
 
 pub struct TheGrind<'a> {
-    pub irc_client: IRCClient,
+    pub outbox: Outbox,
+    pub streams: Streams,
     pub db_pool: SqlitePool,
     pub templates_env: Environment<'a>,
 }
@@ -29,9 +32,13 @@ impl TheGrind<'_> {
 }
 
 impl TheGrind<'_> {
-    pub async fn handle(&self, server_message: &ServerMessage) {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
         if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
-            match Reply::matching(self.handler(), message, &self.db_pool).await.as_slice() {
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
                 [reply @ Reply {
                     additional_inputs: Some(additional_inputs),
                     ..
@@ -62,14 +69,19 @@ impl TheGrind<'_> {
                             let template_inputs = TemplateInputs::from(summoner_json);
 
                             match reply
-                                .render_template(&self.templates_env, Some(&Value::from_serialize(&template_inputs)))
+                                .render_template(
+                                    &self.templates_env,
+                                    channel,
+                                    &self.streams,
+                                    &self.db_pool,
+                                    Some(&Value::from_serialize(&template_inputs)),
+                                )
+                                .await
                             {
                                 Ok(rendered_reply) if rendered_reply.is_empty() => {
                                     eprintln!("Rendered reply template empty: {:?}.", reply)
                                 }
-                                Ok(rendered_reply) => {
-                                    self.irc_client.say_in_reply_to(message, rendered_reply).await.unwrap()
-                                }
+                                Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
                                 Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
                             }
                         }
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use minijinja::value::Value;
+use minijinja::Environment;
+use sqlx::SqlitePool;
+use tokio::time::Instant;
+use twitch_irc::message::ServerMessage;
+
+use crate::handlers::persistence::Channel;
+use crate::handlers::timers::persistence::Timer;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+use crate::templates_env;
+
+pub const TICK: Duration = Duration::from_secs(10);
+
+/// Posts the templates of the enabled timers on schedule, rotating through them.
+///
+/// A timer fires once its interval has passed and at least `min_chat_messages` chat messages arrived since its last
+/// post. Live only timers are skipped while the stream is offline.
+pub struct Timers<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+    chat_messages_counts: Mutex<HashMap<String, u64>>,
+    timers_states: Mutex<HashMap<i64, TimerState>>,
+}
+
+impl<'a> Timers<'a> {
+    pub fn new(outbox: Outbox, streams: Streams, db_pool: SqlitePool, templates_env: Environment<'a>) -> Self {
+        Self {
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            chat_messages_counts: Mutex::new(HashMap::new()),
+            timers_states: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Timers<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage) {
+        if let ServerMessage::Privmsg(message) = server_message {
+            match self.chat_messages_counts.lock() {
+                Ok(mut counts) => *counts.entry(message.channel_login.clone()).or_default() += 1,
+                Err(error) => eprintln!("Cannot get chat messages counts Lock, error: {:?}", error),
+            }
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            self.fire_due_timers(Instant::now()).await;
+        }
+    }
+
+    async fn fire_due_timers(&self, now: Instant) {
+        let timers = match Timer::all_enabled(&self.db_pool).await {
+            Ok(timers) => timers,
+            Err(error) => {
+                eprintln!("Error loading timers, error: {:?}", error);
+                return;
+            }
+        };
+
+        for timer in timers {
+            let Some(template) = self.next_due_template(&timer, now) else {
+                continue;
+            };
+
+            let channel = Channel::by_name_or_default(&timer.channel, &self.db_pool).await;
+            match templates_env::render_template::<Value>(
+                &self.templates_env,
+                &template,
+                &channel,
+                &self.streams,
+                &self.db_pool,
+                None,
+            )
+            .await
+            {
+                Ok(rendered_timer) if rendered_timer.is_empty() => {
+                    eprintln!("Rendered timer template empty: {:?}.", timer)
+                }
+                Ok(rendered_timer) => self.outbox.say(&timer.channel, rendered_timer),
+                Err(e) => eprintln!("Error rendering timer template, error: {:?}, {:?}.", timer, e),
+            }
+        }
+    }
+
+    fn next_due_template(&self, timer: &Timer, now: Instant) -> Option<String> {
+        let chat_messages_count = self
+            .chat_messages_counts
+            .lock()
+            .ok()
+            .and_then(|counts| counts.get(&timer.channel).copied())
+            .unwrap_or_default();
+        let is_live = self.streams.is_live(&timer.channel);
+
+        let mut timers_states = self
+            .timers_states
+            .lock()
+            .map_err(|error| eprintln!("Cannot get timers states Lock, error: {:?}", error))
+            .ok()?;
+
+        let timer_state = timers_states
+            .entry(timer.id)
+            .or_insert_with(|| TimerState::new(now, chat_messages_count));
+
+        if !timer_state.is_due(timer, now, chat_messages_count, is_live) {
+            return None;
+        }
+
+        timer_state.fire(timer, now, chat_messages_count)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TimerState {
+    last_fired_at: Instant,
+    chat_messages_count_at_last_fire: u64,
+    next_template_idx: usize,
+}
+
+impl TimerState {
+    pub fn new(now: Instant, chat_messages_count: u64) -> Self {
+        Self {
+            last_fired_at: now,
+            chat_messages_count_at_last_fire: chat_messages_count,
+            next_template_idx: 0,
+        }
+    }
+
+    pub fn is_due(&self, timer: &Timer, now: Instant, chat_messages_count: u64, is_live: Option<bool>) -> bool {
+        let min_chat_messages = timer.min_chat_messages.max(0) as u64;
+
+        !timer.templates.is_empty()
+            && now.duration_since(self.last_fired_at) >= timer.interval()
+            && chat_messages_count.saturating_sub(self.chat_messages_count_at_last_fire) >= min_chat_messages
+            && (!timer.live_only || is_live == Some(true))
+    }
+
+    pub fn fire(&mut self, timer: &Timer, now: Instant, chat_messages_count: u64) -> Option<String> {
+        let template = timer
+            .templates
+            .get(self.next_template_idx % timer.templates.len().max(1))
+            .cloned();
+
+        self.last_fired_at = now;
+        self.chat_messages_count_at_last_fire = chat_messages_count;
+        self.next_template_idx = (self.next_template_idx + 1) % timer.templates.len().max(1);
+
+        template
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::Fake;
+    use fake::Faker;
+    use sqlx::types::Json;
+
+    use super::*;
+
+    #[test]
+    fn test_timer_state_is_due_works_as_expected() {
+        let timer = dummy_timer(vec!["foo".into()]);
+        let start = Instant::now();
+        let timer_state = TimerState::new(start, 10);
+
+        assert!(!timer_state.is_due(&timer, start, 20, Some(true)));
+        assert!(!timer_state.is_due(&timer, start + Duration::from_secs(60), 14, Some(true)));
+        assert!(timer_state.is_due(&timer, start + Duration::from_secs(60), 15, Some(true)));
+        assert!(!timer_state.is_due(&timer, start + Duration::from_secs(60), 15, Some(false)));
+        assert!(!timer_state.is_due(&timer, start + Duration::from_secs(60), 15, None));
+
+        let offline_timer = Timer {
+            live_only: false,
+            ..timer.clone()
+        };
+        assert!(timer_state.is_due(&offline_timer, start + Duration::from_secs(60), 15, None));
+
+        let empty_timer = dummy_timer(vec![]);
+        assert!(!timer_state.is_due(&empty_timer, start + Duration::from_secs(60), 15, Some(true)));
+    }
+
+    #[test]
+    fn test_timer_state_fire_rotates_templates() {
+        let timer = dummy_timer(vec!["foo".into(), "bar".into()]);
+        let start = Instant::now();
+        let mut timer_state = TimerState::new(start, 0);
+
+        assert_eq!(Some("foo".into()), timer_state.fire(&timer, start, 5));
+        assert_eq!(
+            TimerState {
+                last_fired_at: start,
+                chat_messages_count_at_last_fire: 5,
+                next_template_idx: 1
+            },
+            timer_state
+        );
+        assert_eq!(Some("bar".into()), timer_state.fire(&timer, start, 5));
+        assert_eq!(Some("foo".into()), timer_state.fire(&timer, start, 5));
+    }
+
+    fn dummy_timer(templates: Vec<String>) -> Timer {
+        Timer {
+            id: Faker.fake(),
+            channel: Faker.fake(),
+            templates: Json(templates),
+            interval_secs: 60,
+            min_chat_messages: 5,
+            live_only: true,
+            enabled: true,
+            created_by: Faker.fake(),
+            created_at: Faker.fake(),
+            updated_at: Faker.fake(),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+use sqlx::types::Json;
+
+#[derive(Debug, Clone)]
+pub struct Timer {
+    pub id: i64,
+    pub channel: String,
+    pub templates: Json<Vec<String>>,
+    pub interval_secs: i64,
+    pub min_chat_messages: i64,
+    pub live_only: bool,
+    pub enabled: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Timer {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(0) as u64)
+    }
+
+    pub async fn all_enabled<'a>(executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    id,
+                    channel,
+                    templates as "templates!: Json<Vec<String>>",
+                    interval_secs,
+                    min_chat_messages,
+                    live_only,
+                    enabled,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from timers
+                where enabled = 1
+                order by id asc
+            "#
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,792 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
+use minijinja::value::Value;
+use minijinja::Environment;
+use rand::Rng;
+use regex::RegexBuilder;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::ddragon::champion::Champion;
+use crate::apis::ddragon::champion::Tag;
+use crate::apis::twitch;
+use crate::handlers::persistence::capture;
+use crate::handlers::persistence::required_capture;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::MatchableMessage;
+use crate::handlers::persistence::Reply;
+use crate::handlers::points::persistence::UserPoints;
+use crate::handlers::trivia::persistence::TriviaScore;
+use crate::handlers::trivia::persistence::TriviaSettings;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+pub const TICK: Duration = Duration::from_secs(1);
+/// Seconds between the end of a round and the clue of the next one.
+pub const PAUSE_SECS: i64 = 5;
+pub const MAX_ROUNDS: i64 = 20;
+pub const LEADERBOARD_LEN: i64 = 5;
+pub const REDACTED: &str = "___";
+
+/// League champion trivia built on the champions imported with `xtask import-ddragon-champion`, e.g.
+/// `!trivia start <rounds>`, `!trivia stop` and `!trivia top`.
+///
+/// The commands reply pattern must capture the `action` (`start`, `stop` or `top`) and, optionally, the `rounds`.
+/// Only the streamer and the mods can start and stop games.
+/// Each round posts a clue (title, redacted blurb, tags or stats) and reveals a letter of the champion name every
+/// `hint_secs`: the first chatter typing the name wins the round and the configured loyalty points.
+/// The game messages are the [`Handler::Trivia`] replies matching `trivia.clue`, `trivia.hint`, `trivia.correct`,
+/// `trivia.timeout` and `trivia.end`.
+pub struct Trivia<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+    games: Mutex<HashMap<String, Game>>,
+}
+
+impl<'a> Trivia<'a> {
+    pub fn new(outbox: Outbox, streams: Streams, db_pool: SqlitePool, templates_env: Environment<'a>) -> Self {
+        Self {
+            outbox,
+            streams,
+            db_pool,
+            templates_env,
+            games: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn handler(&self) -> Handler {
+        Handler::TriviaCommands
+    }
+}
+
+impl Trivia<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if self.answer(message).await {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let trivia_command = match TriviaCommand::try_from(&reply.captures(message)) {
+                        Ok(trivia_command) => trivia_command,
+                        Err(error) => {
+                            eprintln!(
+                                "Error parsing trivia command for message: {:?}, error: {:?}.",
+                                message.message_text, error
+                            );
+                            return;
+                        }
+                    };
+
+                    let template_inputs = match self.execute(message, &trivia_command).await {
+                        Ok(Some(template_inputs)) => template_inputs,
+                        Ok(None) => return,
+                        Err(error) => {
+                            eprintln!(
+                                "Error executing trivia command {:?}, error: {:?}.",
+                                trivia_command, error
+                            );
+                            return;
+                        }
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+
+    /// Advances the running games every [`TICK`], posting clues, hints and answers.
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            let channels: Vec<String> = match self.games.lock() {
+                Ok(games) => games.keys().cloned().collect(),
+                Err(error) => {
+                    eprintln!("Cannot get trivia games Lock, error: {:?}", error);
+                    continue;
+                }
+            };
+            for channel in channels {
+                self.advance(&channel, Utc::now()).await;
+            }
+        }
+    }
+
+    async fn advance(&self, channel: &str, now: DateTime<Utc>) {
+        let step = match self.games.lock() {
+            Ok(mut games) => match games.get_mut(channel) {
+                Some(game) => game.step(now),
+                None => return,
+            },
+            Err(error) => {
+                eprintln!("Cannot get trivia games Lock, error: {:?}", error);
+                return;
+            }
+        };
+
+        match step {
+            Some(Step::NextRound) => {
+                let champion = match Champion::random(&self.db_pool).await {
+                    Ok(Some(champion)) => champion,
+                    Ok(None) => {
+                        eprintln!("No champions to play trivia in channel {:?}.", channel);
+                        self.end(channel);
+                        return;
+                    }
+                    Err(error) => {
+                        eprintln!("Error loading trivia champion, error: {:?}", error);
+                        return;
+                    }
+                };
+                let clue = Clue::random(&champion, &mut rand::thread_rng());
+                let template_inputs = match self.games.lock() {
+                    Ok(mut games) => match games.get_mut(channel) {
+                        Some(game) => game.start_round(champion, clue, now),
+                        None => return,
+                    },
+                    Err(error) => {
+                        eprintln!("Cannot get trivia games Lock, error: {:?}", error);
+                        return;
+                    }
+                };
+                self.say(channel, "trivia.clue", &template_inputs).await;
+            }
+            Some(Step::Hint(template_inputs)) => self.say(channel, "trivia.hint", &template_inputs).await,
+            Some(Step::Timeout(template_inputs)) => self.say(channel, "trivia.timeout", &template_inputs).await,
+            Some(Step::End(template_inputs)) => {
+                self.end(channel);
+                self.say(channel, "trivia.end", &template_inputs).await;
+            }
+            None => {}
+        }
+    }
+
+    /// Whether the message is the right answer of the running round.
+    async fn answer(&self, message: &PrivmsgMessage) -> bool {
+        let (template_inputs, points) = match self.games.lock() {
+            Ok(mut games) => match games.get_mut(&message.channel_login) {
+                Some(game) => match game.answer(&message.sender.login, &message.sender.name, &message.message_text) {
+                    Some(template_inputs) => (template_inputs, game.settings.points),
+                    None => return false,
+                },
+                None => return false,
+            },
+            Err(error) => {
+                eprintln!("Cannot get trivia games Lock, error: {:?}", error);
+                return false;
+            }
+        };
+
+        let channel = message.channel_login.as_str();
+        if let Err(error) = TriviaScore::incr(channel, &message.sender.login, &message.sender.name, &self.db_pool).await
+        {
+            eprintln!(
+                "Error incrementing trivia score {:?}, error: {:?}",
+                message.sender, error
+            );
+        }
+        if points > 0 {
+            if let Err(error) = UserPoints::add(
+                channel,
+                &message.sender.login,
+                &message.sender.name,
+                points,
+                &self.db_pool,
+            )
+            .await
+            {
+                eprintln!("Error adding trivia points {:?}, error: {:?}", message.sender, error);
+            }
+        }
+
+        self.say(channel, "trivia.correct", &template_inputs).await;
+        true
+    }
+
+    async fn execute(
+        &self,
+        message: &PrivmsgMessage,
+        trivia_command: &TriviaCommand,
+    ) -> anyhow::Result<Option<TemplateInputs>> {
+        let channel = message.channel_login.as_str();
+        let mut template_inputs = TemplateInputs::default();
+
+        match trivia_command {
+            TriviaCommand::Start { rounds } => {
+                if !twitch::helpers::is_from_streamer_or_mod(message) {
+                    return Ok(None);
+                }
+                let settings = TriviaSettings::by_channel_or_default(channel, &self.db_pool).await?;
+                let game = Game::new(settings, *rounds, Utc::now());
+                template_inputs.rounds = game.rounds;
+
+                let mut games = self
+                    .games
+                    .lock()
+                    .map_err(|error| anyhow!("Cannot get trivia games Lock, error: {:?}", error))?;
+                if games.contains_key(channel) {
+                    bail!("Trivia already running in channel {:?}.", channel);
+                }
+                games.insert(channel.into(), game);
+            }
+            TriviaCommand::Stop => {
+                if !twitch::helpers::is_from_streamer_or_mod(message) {
+                    return Ok(None);
+                }
+                let game = self
+                    .games
+                    .lock()
+                    .map_err(|error| anyhow!("Cannot get trivia games Lock, error: {:?}", error))?
+                    .remove(channel)
+                    .ok_or_else(|| anyhow!("No trivia running in channel {:?}.", channel))?;
+                template_inputs = game.template_inputs();
+                template_inputs.answer = game.round.map(|r| r.champion.name);
+            }
+            TriviaCommand::Top => {
+                template_inputs.leaderboard =
+                    TriviaScore::top_by_channel(channel, LEADERBOARD_LEN, &self.db_pool).await?;
+            }
+        }
+
+        template_inputs.action = Some(trivia_command.action());
+        Ok(Some(template_inputs))
+    }
+
+    fn end(&self, channel: &str) {
+        match self.games.lock() {
+            Ok(mut games) => {
+                games.remove(channel);
+            }
+            Err(error) => eprintln!("Cannot get trivia games Lock, error: {:?}", error),
+        }
+    }
+
+    async fn say(&self, channel: &str, kind: &'static str, template_inputs: &TemplateInputs) {
+        let trivia_event = TriviaEvent {
+            channel: channel.into(),
+            kind,
+        };
+
+        let channel = Channel::by_name_or_default(&trivia_event.channel, &self.db_pool).await;
+        let is_live = self.streams.is_live(&trivia_event.channel);
+        match Reply::matching(Handler::Trivia, &trivia_event, &channel, is_live, &self.db_pool)
+            .await
+            .as_slice()
+        {
+            [reply] => match reply
+                .render_template(
+                    &self.templates_env,
+                    &channel,
+                    &self.streams,
+                    &self.db_pool,
+                    Some(&Value::from_serialize(template_inputs)),
+                )
+                .await
+            {
+                Ok(rendered_reply) if rendered_reply.is_empty() => {
+                    eprintln!("Rendered reply template empty: {:?}.", reply)
+                }
+                Ok(rendered_reply) => self.outbox.say(&trivia_event.channel, rendered_reply),
+                Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+            },
+            [] => {}
+            multiple_matching_replies => eprintln!(
+                "Multiple matching replies for trivia event: {:?}, {:?}.",
+                multiple_matching_replies, trivia_event
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TriviaEvent {
+    channel: String,
+    kind: &'static str,
+}
+
+impl MatchableMessage for TriviaEvent {
+    fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    fn text(&self) -> &str {
+        self.kind
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Game {
+    settings: TriviaSettings,
+    rounds: i64,
+    rounds_played: i64,
+    round: Option<Round>,
+    next_round_at: DateTime<Utc>,
+    /// Right answers of this game by user name.
+    standings: HashMap<String, i64>,
+}
+
+#[derive(Debug, Clone)]
+struct Round {
+    champion: Champion,
+    clue: Clue,
+    started_at: DateTime<Utc>,
+    hints: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    NextRound,
+    Hint(TemplateInputs),
+    Timeout(TemplateInputs),
+    End(TemplateInputs),
+}
+
+impl Game {
+    fn new(settings: TriviaSettings, rounds: Option<i64>, now: DateTime<Utc>) -> Self {
+        Self {
+            rounds: rounds.unwrap_or(settings.rounds).clamp(1, MAX_ROUNDS),
+            settings,
+            rounds_played: 0,
+            round: None,
+            next_round_at: now,
+            standings: HashMap::new(),
+        }
+    }
+
+    /// What the game has to do next, if anything.
+    fn step(&mut self, now: DateTime<Utc>) -> Option<Step> {
+        let Some(round) = &mut self.round else {
+            if now < self.next_round_at {
+                return None;
+            }
+            if self.rounds_played >= self.rounds {
+                return Some(Step::End(self.template_inputs()));
+            }
+            return Some(Step::NextRound);
+        };
+
+        let elapsed_secs = (now - round.started_at).num_seconds();
+        if elapsed_secs >= self.settings.round_secs {
+            let answer = round.champion.name.clone();
+            self.end_round(now);
+            return Some(Step::Timeout(TemplateInputs {
+                answer: Some(answer),
+                ..self.template_inputs()
+            }));
+        }
+
+        let hints = elapsed_secs / self.settings.hint_secs.max(1);
+        if hints > round.hints {
+            round.hints = hints;
+            return Some(Step::Hint(self.template_inputs()));
+        }
+
+        None
+    }
+
+    fn start_round(&mut self, champion: Champion, clue: Clue, now: DateTime<Utc>) -> TemplateInputs {
+        self.rounds_played += 1;
+        self.round = Some(Round {
+            champion,
+            clue,
+            started_at: now,
+            hints: 0,
+        });
+        self.template_inputs()
+    }
+
+    /// Ends the running round if the guess is right.
+    fn answer(&mut self, user_login: &str, user_name: &str, guess: &str) -> Option<TemplateInputs> {
+        let champion = &self.round.as_ref()?.champion;
+        if !is_answer(guess, champion) {
+            return None;
+        }
+
+        let answer = champion.name.clone();
+        *self.standings.entry(user_name.into()).or_default() += 1;
+        self.end_round(Utc::now());
+
+        Some(TemplateInputs {
+            answer: Some(answer),
+            winner: Some(user_name.into()),
+            winner_login: Some(user_login.into()),
+            points: Some(self.settings.points),
+            ..self.template_inputs()
+        })
+    }
+
+    fn end_round(&mut self, now: DateTime<Utc>) {
+        self.round = None;
+        self.next_round_at = now + chrono::Duration::seconds(PAUSE_SECS);
+    }
+
+    fn template_inputs(&self) -> TemplateInputs {
+        let mut standings: Vec<Standing> = self
+            .standings
+            .iter()
+            .map(|(user_name, wins)| Standing {
+                user_name: user_name.clone(),
+                wins: *wins,
+            })
+            .collect();
+        standings.sort_by(|a, b| b.wins.cmp(&a.wins).then_with(|| a.user_name.cmp(&b.user_name)));
+
+        TemplateInputs {
+            round: self.rounds_played,
+            rounds: self.rounds,
+            clue: self.round.as_ref().map(|r| r.clue.clone()),
+            hint: self.round.as_ref().map(|r| hint(&r.champion.name, r.hints)),
+            standings,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Clue {
+    Title {
+        title: String,
+    },
+    Blurb {
+        blurb: String,
+    },
+    Tags {
+        tags: Vec<Tag>,
+        partype: String,
+    },
+    Stats {
+        attack: i64,
+        defense: i64,
+        magic: i64,
+        difficulty: i64,
+    },
+}
+
+impl Clue {
+    pub fn random<R: Rng + ?Sized>(champion: &Champion, rng: &mut R) -> Self {
+        match rng.gen_range(0..4) {
+            0 => Self::Title {
+                title: champion.title.clone(),
+            },
+            1 => Self::Blurb {
+                blurb: redact(&champion.blurb, champion),
+            },
+            2 => Self::Tags {
+                tags: champion.tags.clone(),
+                partype: champion.partype.clone(),
+            },
+            _ => Self::Stats {
+                attack: champion.info.attack,
+                defense: champion.info.defense,
+                magic: champion.info.magic,
+                difficulty: champion.info.difficulty,
+            },
+        }
+    }
+}
+
+/// Replaces the name of the champion, and each of its words, with [`REDACTED`].
+fn redact(text: &str, champion: &Champion) -> String {
+    let mut words: Vec<&str> = champion
+        .name
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| w.chars().count() > 2)
+        .collect();
+    words.push(&champion.name);
+    words.push(&champion.id);
+    words.sort_by_key(|w| std::cmp::Reverse(w.len()));
+
+    let pattern = words.iter().map(|w| regex::escape(w)).collect::<Vec<_>>().join("|");
+    match RegexBuilder::new(&format!(r"\b(?:{})\b", pattern))
+        .case_insensitive(true)
+        .build()
+    {
+        Ok(re) => re.replace_all(text, REDACTED).into_owned(),
+        Err(error) => {
+            eprintln!(
+                "Invalid redaction pattern for champion {:?}, error: {:?}",
+                champion.name, error
+            );
+            text.into()
+        }
+    }
+}
+
+/// The name with only its first `revealed` letters visible, never revealing the whole name.
+fn hint(name: &str, revealed: i64) -> String {
+    let letters = name.chars().filter(|c| c.is_alphanumeric()).count() as i64;
+    let revealed = revealed.min(letters - 1).max(0);
+
+    let mut seen = 0;
+    name.chars()
+        .map(|c| {
+            if !c.is_alphanumeric() {
+                return c;
+            }
+            seen += 1;
+            if seen <= revealed {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_answer(guess: &str, champion: &Champion) -> bool {
+    let guess = normalize(guess);
+    !guess.is_empty() && (guess == normalize(&champion.name) || guess == normalize(&champion.id))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriviaCommand {
+    Start { rounds: Option<i64> },
+    Stop,
+    Top,
+}
+
+impl TriviaCommand {
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Start { .. } => "start",
+            Self::Stop => "stop",
+            Self::Top => "top",
+        }
+    }
+}
+
+impl TryFrom<&HashMap<String, String>> for TriviaCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(captures: &HashMap<String, String>) -> Result<Self, Self::Error> {
+        Ok(match required_capture(captures, "action")?.to_lowercase().as_str() {
+            "start" | "play" => {
+                let rounds = capture(captures, "rounds").map(str::parse::<i64>).transpose()?;
+                if let Some(rounds) = rounds.filter(|r| !(1..=MAX_ROUNDS).contains(r)) {
+                    bail!("Expected between 1 and {} rounds, got {:?}.", MAX_ROUNDS, rounds);
+                }
+                Self::Start { rounds }
+            }
+            "stop" | "end" => Self::Stop,
+            "top" | "leaderboard" => Self::Top,
+            unexpected_action => bail!("Unexpected trivia action {:?}.", unexpected_action),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct Standing {
+    user_name: String,
+    wins: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+struct TemplateInputs {
+    action: Option<&'static str>,
+    round: i64,
+    rounds: i64,
+    clue: Option<Clue>,
+    /// The champion name with the letters revealed so far.
+    hint: Option<String>,
+    answer: Option<String>,
+    winner: Option<String>,
+    winner_login: Option<String>,
+    points: Option<i64>,
+    /// Right answers of the current game.
+    standings: Vec<Standing>,
+    leaderboard: Vec<TriviaScore>,
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::Fake;
+    use fake::Faker;
+
+    use super::*;
+    use crate::handlers::persistence::test_captures;
+
+    #[test]
+    fn test_trivia_command_try_from_captures_works_as_expected() {
+        assert_eq!(
+            TriviaCommand::Start { rounds: Some(3) },
+            TriviaCommand::try_from(&test_captures(&[("action", "start"), ("rounds", "3")])).unwrap()
+        );
+        assert_eq!(
+            TriviaCommand::Start { rounds: None },
+            TriviaCommand::try_from(&test_captures(&[("action", "Play")])).unwrap()
+        );
+        assert!(TriviaCommand::try_from(&test_captures(&[("action", "start"), ("rounds", "42")])).is_err());
+        assert_eq!(
+            TriviaCommand::Stop,
+            TriviaCommand::try_from(&test_captures(&[("action", "stop")])).unwrap()
+        );
+        assert_eq!(
+            TriviaCommand::Top,
+            TriviaCommand::try_from(&test_captures(&[("action", "leaderboard")])).unwrap()
+        );
+        assert!(TriviaCommand::try_from(&test_captures(&[("action", "foo")])).is_err());
+    }
+
+    #[test]
+    fn test_redact_works_as_expected() {
+        let champion = dummy_champion("Miss Fortune", "MissFortune");
+
+        assert_eq!(
+            "___ is a captain. Sarah ___ hunts ___.",
+            redact("Miss Fortune is a captain. Sarah Fortune hunts missfortune.", &champion)
+        );
+        assert_eq!("Fortunes", redact("Fortunes", &champion));
+    }
+
+    #[test]
+    fn test_hint_works_as_expected() {
+        assert_eq!("___'__", hint("Kai'Sa", 0));
+        assert_eq!("Ka_'__", hint("Kai'Sa", 2));
+        assert_eq!("Kai'S_", hint("Kai'Sa", 42));
+        assert_eq!("N___ & _______", hint("Nunu & Willump", 1));
+    }
+
+    #[test]
+    fn test_is_answer_works_as_expected() {
+        let champion = dummy_champion("Kai'Sa", "Kaisa");
+
+        assert!(is_answer("kaisa", &champion));
+        assert!(is_answer(" Kai'Sa ", &champion));
+        assert!(is_answer("KAI SA", &champion));
+        assert!(!is_answer("kai", &champion));
+        assert!(!is_answer("!!", &champion));
+    }
+
+    #[test]
+    fn test_game_step_works_as_expected() {
+        let now = Utc::now();
+        let settings = TriviaSettings {
+            channel: "foo".into(),
+            rounds: 5,
+            round_secs: 60,
+            hint_secs: 15,
+            points: 50,
+        };
+        let mut game = Game::new(settings, Some(1), now);
+
+        assert_eq!(Some(Step::NextRound), game.step(now));
+        game.start_round(
+            dummy_champion("Ahri", "Ahri"),
+            Clue::Title {
+                title: "the Nine-Tailed Fox".into(),
+            },
+            now,
+        );
+        assert_eq!(None, game.step(now + chrono::Duration::seconds(10)));
+
+        let Some(Step::Hint(template_inputs)) = game.step(now + chrono::Duration::seconds(15)) else {
+            panic!("Expected hint");
+        };
+        assert_eq!(Some("A___".into()), template_inputs.hint);
+        assert_eq!(None, game.step(now + chrono::Duration::seconds(20)));
+
+        let Some(Step::Timeout(template_inputs)) = game.step(now + chrono::Duration::seconds(60)) else {
+            panic!("Expected timeout");
+        };
+        assert_eq!(Some("Ahri".into()), template_inputs.answer);
+
+        assert_eq!(None, game.step(now + chrono::Duration::seconds(61)));
+        assert!(matches!(
+            game.step(now + chrono::Duration::seconds(60 + PAUSE_SECS)),
+            Some(Step::End(_))
+        ));
+    }
+
+    #[test]
+    fn test_game_answer_works_as_expected() {
+        let now = Utc::now();
+        let mut game = Game::new(
+            TriviaSettings {
+                channel: "foo".into(),
+                rounds: 5,
+                round_secs: 60,
+                hint_secs: 15,
+                points: 50,
+            },
+            None,
+            now,
+        );
+
+        assert_eq!(None, game.answer("bar", "Bar", "Ahri"));
+        game.start_round(
+            dummy_champion("Ahri", "Ahri"),
+            Clue::Title {
+                title: "the Nine-Tailed Fox".into(),
+            },
+            now,
+        );
+        assert_eq!(None, game.answer("bar", "Bar", "Akali"));
+
+        let template_inputs = game.answer("bar", "Bar", "ahri").unwrap();
+        assert_eq!(Some("Ahri".into()), template_inputs.answer);
+        assert_eq!(Some("Bar".into()), template_inputs.winner);
+        assert_eq!(Some(50), template_inputs.points);
+        assert_eq!(
+            vec![Standing {
+                user_name: "Bar".into(),
+                wins: 1
+            }],
+            template_inputs.standings
+        );
+        assert_eq!(None, game.answer("baz", "Baz", "ahri"));
+    }
+
+    fn dummy_champion(name: &str, id: &str) -> Champion {
+        Champion {
+            name: name.into(),
+            id: id.into(),
+            ..Faker.fake()
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+/// Per channel tuning of the trivia games.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriviaSettings {
+    pub channel: String,
+    pub rounds: i64,
+    pub round_secs: i64,
+    /// Seconds between two hints of the same round.
+    pub hint_secs: i64,
+    /// Loyalty points awarded for each right answer.
+    pub points: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TriviaScore {
+    pub channel: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub wins: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TriviaSettings {
+    /// The configured settings or the defaults of the table for channels without them.
+    pub async fn by_channel_or_default<'a>(channel: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Self> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    rounds,
+                    round_secs,
+                    hint_secs,
+                    points
+                from trivia_settings
+                where channel = $1
+            "#,
+            channel,
+        )
+        .fetch_optional(executor)
+        .await?
+        .unwrap_or_else(|| Self {
+            channel: channel.into(),
+            rounds: 5,
+            round_secs: 60,
+            hint_secs: 15,
+            points: 50,
+        }))
+    }
+}
+
+impl TriviaScore {
+    pub async fn incr<'a>(
+        channel: &str,
+        user_login: &str,
+        user_name: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into trivia_scores (channel, user_login, user_name, wins)
+                values ($1, $2, $3, 1)
+                on conflict (channel, user_login) do update set
+                    user_name = excluded.user_name,
+                    wins = wins + 1,
+                    updated_at = current_timestamp
+                returning
+                    channel,
+                    user_login,
+                    user_name,
+                    wins,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            user_login,
+            user_name,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn top_by_channel<'a>(
+        channel: &str,
+        limit: i64,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    user_login,
+                    user_name,
+                    wins,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from trivia_scores
+                where channel = $1
+                order by wins desc
+                limit $2
+            "#,
+            channel,
+            limit,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,112 @@
+use minijinja::value::Value;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+
+use crate::apis::twitch;
+use crate::handlers::persistence::Channel;
+use crate::handlers::persistence::Handler;
+use crate::handlers::persistence::Reply;
+use crate::handlers::vars::persistence::Var;
+use crate::outbox::Outbox;
+use crate::streams::Streams;
+
+/// Mod commands to set or unset per channel vars.
+///
+/// The reply pattern must capture the var `key` and, optionally, the `value` to set it to.
+/// Without a `value` the var is unset.
+pub struct Vars<'a> {
+    pub outbox: Outbox,
+    pub streams: Streams,
+    pub db_pool: SqlitePool,
+    pub templates_env: Environment<'a>,
+}
+
+impl Vars<'_> {
+    pub fn handler(&self) -> Handler {
+        Handler::Vars
+    }
+}
+
+impl Vars<'_> {
+    pub async fn handle(&self, server_message: &ServerMessage, channel: &Channel) {
+        if let ServerMessage::Privmsg(message @ PrivmsgMessage { is_action: false, .. }) = server_message {
+            if !twitch::helpers::is_from_streamer_or_mod(message) {
+                return;
+            }
+
+            let is_live = self.streams.is_live(&message.channel_login);
+            match Reply::matching(self.handler(), message, channel, is_live, &self.db_pool)
+                .await
+                .as_slice()
+            {
+                [reply] => {
+                    let captures = reply.captures(message);
+
+                    let Some(key) = captures.get("key") else {
+                        eprintln!("Missing var key capture in reply: {:?}.", reply);
+                        return;
+                    };
+
+                    let template_inputs = match captures.get("value").map(|v| v.trim()) {
+                        Some(value) if !value.is_empty() => {
+                            match Var::set(&message.channel_login, key, value, &message.sender.login, &self.db_pool)
+                                .await
+                            {
+                                Ok(var) => TemplateInputs {
+                                    key: var.key,
+                                    value: Some(var.value),
+                                },
+                                Err(error) => {
+                                    eprintln!("Error setting var {:?}, error: {:?}.", key, error);
+                                    return;
+                                }
+                            }
+                        }
+                        _ => match Var::unset(&message.channel_login, key, &self.db_pool).await {
+                            Ok(_) => TemplateInputs {
+                                key: key.clone(),
+                                value: None,
+                            },
+                            Err(error) => {
+                                eprintln!("Error unsetting var {:?}, error: {:?}.", key, error);
+                                return;
+                            }
+                        },
+                    };
+
+                    match reply
+                        .render_template(
+                            &self.templates_env,
+                            channel,
+                            &self.streams,
+                            &self.db_pool,
+                            Some(&Value::from_serialize(&template_inputs)),
+                        )
+                        .await
+                    {
+                        Ok(rendered_reply) if rendered_reply.is_empty() => {
+                            eprintln!("Rendered reply template empty: {:?}.", reply)
+                        }
+                        Ok(rendered_reply) => self.outbox.say_in_reply_to(message, rendered_reply),
+                        Err(e) => eprintln!("Error rendering reply template, error: {:?}, {:?}.", reply, e),
+                    }
+                }
+                [] => {}
+                multiple_matching_replies => eprintln!(
+                    "Multiple matching replies for message: {:?}, {:?}.",
+                    multiple_matching_replies, server_message
+                ),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TemplateInputs {
+    pub key: String,
+    pub value: Option<String>,
+}
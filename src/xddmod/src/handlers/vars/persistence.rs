@@ -0,0 +1,98 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::sqlite::SqliteExecutor;
+use sqlx::types::chrono::DateTime;
+use sqlx::types::chrono::Utc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Var {
+    pub channel: String,
+    pub key: String,
+    pub value: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Var {
+    pub async fn get<'a>(channel: &str, key: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    key,
+                    value,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from vars
+                where channel = $1 and key = $2
+            "#,
+            channel,
+            key,
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn all_by_channel<'a>(channel: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                select
+                    channel,
+                    key,
+                    value,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                from vars
+                where channel = $1
+            "#,
+            channel,
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn set<'a>(
+        channel: &str,
+        key: &str,
+        value: &str,
+        created_by: &str,
+        executor: impl SqliteExecutor<'a>,
+    ) -> sqlx::Result<Self> {
+        sqlx::query_as!(
+            Self,
+            r#"
+                insert into vars (channel, key, value, created_by)
+                values ($1, $2, $3, $4)
+                on conflict (channel, key) do update set
+                    value = excluded.value,
+                    created_by = excluded.created_by,
+                    updated_at = current_timestamp
+                returning
+                    channel,
+                    key,
+                    value,
+                    created_by,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            channel,
+            key,
+            value,
+            created_by,
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn unset<'a>(channel: &str, key: &str, executor: impl SqliteExecutor<'a>) -> sqlx::Result<bool> {
+        sqlx::query!(r#"delete from vars where channel = $1 and key = $2"#, channel, key)
+            .execute(executor)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+}
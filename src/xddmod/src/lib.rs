@@ -2,7 +2,10 @@ pub mod apis;
 pub mod app_config;
 pub mod auth;
 pub mod handlers;
+pub mod outbox;
 pub mod poor_man_throttling;
+pub mod stats_server;
+pub mod streams;
 pub mod templates_env;
 
 pub use chrono_tz::Tz;
@@ -3,13 +3,42 @@ use std::sync::Arc;
 use sqlx::SqlitePool;
 use tokio::sync::Mutex;
 use twitch_api::HelixClient;
+use twitch_irc::message::ServerMessage;
+use xddmod::apis::twitch::eventsub::EventSubClient;
+use xddmod::apis::twitch::eventsub::Subscription;
 use xddmod::app_config::AppConfig;
 use xddmod::auth;
+use xddmod::handlers::auto_gamba::core::AutoGambas;
+use xddmod::handlers::channel_info_commands::core::ChannelInfoCommands;
+use xddmod::handlers::chat_activity::core::ChatActivity;
+use xddmod::handlers::chat_events::core::ChatEvents;
+use xddmod::handlers::counters::core::Counters;
+use xddmod::handlers::followage::core::Followage;
+use xddmod::handlers::gamba_commands::core::GambaCommands;
+use xddmod::handlers::gamba_stats::core::GambaStats;
+use xddmod::handlers::gamba_time::core::GambaTime;
 use xddmod::handlers::gg::core::Gg;
 use xddmod::handlers::npc::core::Npc;
+use xddmod::handlers::persistence::Channel;
+use xddmod::handlers::points::core::Points;
+use xddmod::handlers::poll_commands::core::PollCommands;
+use xddmod::handlers::poll_time::core::PollTime;
+use xddmod::handlers::quotes::core::Quotes;
+use xddmod::handlers::raffles::core::Raffles;
+use xddmod::handlers::redemptions::core::Redemptions;
+use xddmod::handlers::reminders::core::Reminders;
 use xddmod::handlers::rip_bozo::core::RipBozo;
+use xddmod::handlers::shoutout::core::Shoutout;
 use xddmod::handlers::sniffa::core::Sniffa;
+use xddmod::handlers::stream_markers::core::AutoMarkers;
+use xddmod::handlers::stream_markers::core::StreamMarkers;
 use xddmod::handlers::the_grind::core::TheGrind;
+use xddmod::handlers::timers::core::Timers;
+use xddmod::handlers::trivia::core::Trivia;
+use xddmod::handlers::vars::core::Vars;
+use xddmod::outbox::Outbox;
+use xddmod::streams;
+use xddmod::streams::Streams;
 
 #[tokio::main]
 async fn main() {
@@ -28,10 +57,130 @@ async fn main() {
         .unwrap()
         .unwrap();
 
-    irc_client.join(channel).unwrap();
+    irc_client.join(channel.clone()).unwrap();
+
+    let outbox = Outbox::new(irc_client.clone());
+
+    let streams = Streams::default();
+    streams.start_polling(
+        helix_client.clone(),
+        user_token.clone(),
+        channel.clone(),
+        streams::POLLING_INTERVAL,
+    );
 
     let templates_env = xddmod::templates_env::build_global_templates_env();
 
+    let mut eventsub_client = EventSubClient {
+        ws_url: app_config.eventsub_ws_url.clone(),
+        helix_url: app_config.helix_api_url.clone(),
+        client_id: app_config.client_id.to_string(),
+        token: user_token.clone(),
+        http_client: helix_client.get_client().clone(),
+        subscriptions: Subscription::all_for(broadcaster.id.as_str(), user_token.user_id.as_str()),
+    };
+    let (events_sender, mut incoming_events) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move { eventsub_client.run(events_sender).await });
+
+    let gamba_time = Arc::new(GambaTime::new(
+        user_token.clone(),
+        broadcaster.id.clone(),
+        helix_client.clone(),
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    ));
+    let gamba_commands = Arc::new(Mutex::new(GambaCommands {
+        token: user_token.clone(),
+        broadcaster_id: broadcaster.id.clone(),
+        helix_client: helix_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    }));
+    let poll_time = Arc::new(PollTime::new(
+        user_token.clone(),
+        broadcaster.id.clone(),
+        helix_client.clone(),
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    ));
+    let poll_commands = Arc::new(Mutex::new(PollCommands {
+        token: user_token.clone(),
+        broadcaster_id: broadcaster.id.clone(),
+        helix_client: helix_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    }));
+    let channel_info_commands = Arc::new(Mutex::new(ChannelInfoCommands {
+        token: user_token.clone(),
+        broadcaster_id: broadcaster.id.clone(),
+        helix_client: helix_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    }));
+    let shoutout = Arc::new(Mutex::new(Shoutout::new(
+        user_token.clone(),
+        broadcaster.id.clone(),
+        helix_client.clone(),
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    )));
+    let stream_markers = Arc::new(Mutex::new(StreamMarkers {
+        token: user_token.clone(),
+        broadcaster_id: broadcaster.id.clone(),
+        helix_client: helix_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    }));
+    let auto_markers = AutoMarkers::new(
+        channel.clone(),
+        user_token.clone(),
+        broadcaster.id.clone(),
+        helix_client.clone(),
+        streams.clone(),
+        db_pool.clone(),
+    );
+    tokio::spawn(async move { auto_markers.run().await });
+    let followage = Arc::new(Mutex::new(Followage {
+        token: user_token.clone(),
+        broadcaster_id: broadcaster.id.clone(),
+        helix_client: helix_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    }));
+    let mut redemptions = Redemptions {
+        token: user_token.clone(),
+        broadcaster_id: broadcaster.id.clone(),
+        helix_client: helix_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    };
+    let auto_gambas = AutoGambas::new(
+        channel.clone(),
+        user_token.clone(),
+        broadcaster.id.clone(),
+        helix_client.clone(),
+        db_pool.clone(),
+    );
+    tokio::spawn(async move { auto_gambas.run().await });
+
     let rip_bozo = Arc::new(Mutex::new(RipBozo {
         broadcaster_id: broadcaster.id,
         token: user_token,
@@ -39,47 +188,201 @@ async fn main() {
         db_pool: db_pool.clone(),
     }));
     let npc = Arc::new(Npc {
-        irc_client: irc_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
         db_pool: db_pool.clone(),
         templates_env: templates_env.clone(),
     });
     let gg = Arc::new(Gg {
-        irc_client: irc_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
         db_pool: db_pool.clone(),
         templates_env: templates_env.clone(),
     });
     let sniffa = Arc::new(Sniffa {
-        irc_client: irc_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
         db_pool: db_pool.clone(),
         templates_env: templates_env.clone(),
     });
     let the_grind = Arc::new(TheGrind {
-        irc_client: irc_client.clone(),
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    });
+    let counters = Arc::new(Counters {
+        outbox: outbox.clone(),
+        streams: streams.clone(),
         db_pool: db_pool.clone(),
         templates_env: templates_env.clone(),
     });
+    let chat_events = Arc::new(ChatEvents {
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    });
+    let timers = Arc::new(Timers::new(
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    ));
+    let gamba_stats = Arc::new(GambaStats {
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    });
+    let quotes = Arc::new(Quotes {
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    });
+    let raffles = Arc::new(Raffles::new(
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    ));
+    let points = Arc::new(Points::new(
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    ));
+    let trivia = Arc::new(Trivia::new(
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    ));
+    let chat_activity = Arc::new(ChatActivity::new(
+        outbox.clone(),
+        streams.clone(),
+        db_pool.clone(),
+        templates_env.clone(),
+    ));
+    let reminders = Arc::new(Reminders {
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    });
+    let vars = Arc::new(Vars {
+        outbox: outbox.clone(),
+        streams: streams.clone(),
+        db_pool: db_pool.clone(),
+        templates_env: templates_env.clone(),
+    });
+
+    let timers_runner = timers.clone();
+    tokio::spawn(async move { timers_runner.run().await });
+    let reminders_runner = reminders.clone();
+    tokio::spawn(async move { reminders_runner.run().await });
+    let points_runner = points.clone();
+    tokio::spawn(async move { points_runner.run().await });
+    let trivia_runner = trivia.clone();
+    tokio::spawn(async move { trivia_runner.run().await });
+    tokio::spawn(xddmod::stats_server::serve(
+        app_config.stats_socket_addr,
+        chat_activity.clone(),
+    ));
+
+    let events_streams = streams.clone();
+    let events_gamba_time = gamba_time.clone();
+    let events_poll_time = poll_time.clone();
+    tokio::spawn(async move {
+        while let Some(event) = incoming_events.recv().await {
+            events_streams.handle_event(&event);
+            events_gamba_time.handle_event(&event).await;
+            events_poll_time.handle_event(&event).await;
+            redemptions.handle_event(&event).await;
+        }
+    });
 
     #[allow(clippy::single_match)]
     tokio::spawn(async move {
         while let Some(server_message) = incoming_messages.recv().await {
+            outbox.track_user_state(&server_message);
+
             let rip_bozo = rip_bozo.clone();
             let npc = npc.clone();
             let gg = gg.clone();
             let sniffa = sniffa.clone();
             let the_grind = the_grind.clone();
+            let counters = counters.clone();
+            let vars = vars.clone();
+            let timers = timers.clone();
+            let chat_events = chat_events.clone();
+            let gamba_time = gamba_time.clone();
+            let gamba_commands = gamba_commands.clone();
+            let gamba_stats = gamba_stats.clone();
+            let poll_time = poll_time.clone();
+            let poll_commands = poll_commands.clone();
+            let channel_info_commands = channel_info_commands.clone();
+            let shoutout = shoutout.clone();
+            let stream_markers = stream_markers.clone();
+            let followage = followage.clone();
+            let quotes = quotes.clone();
+            let reminders = reminders.clone();
+            let raffles = raffles.clone();
+            let points = points.clone();
+            let trivia = trivia.clone();
+            let chat_activity = chat_activity.clone();
+            let db_pool = db_pool.clone();
 
             tokio::spawn(async move {
                 let mut rip_bozo_g = rip_bozo.lock().await;
                 if let Ok(true) = rip_bozo_g.handle(&server_message).await {
                     return;
                 }
-                npc.handle(&server_message).await;
-                gg.handle(&server_message).await;
-                sniffa.handle(&server_message).await;
-                the_grind.handle(&server_message).await;
+                let Some(channel_login) = channel_login(&server_message) else {
+                    return;
+                };
+                let channel = Channel::by_name_or_default(channel_login, &db_pool).await;
+
+                npc.handle(&server_message, &channel).await;
+                gg.handle(&server_message, &channel).await;
+                sniffa.handle(&server_message, &channel).await;
+                the_grind.handle(&server_message, &channel).await;
+                counters.handle(&server_message, &channel).await;
+                vars.handle(&server_message, &channel).await;
+                timers.handle(&server_message).await;
+                chat_events.handle(&server_message, &channel).await;
+                gamba_time.handle(&server_message, &channel).await;
+                gamba_commands.lock().await.handle(&server_message, &channel).await;
+                gamba_stats.handle(&server_message, &channel).await;
+                poll_time.handle(&server_message, &channel).await;
+                poll_commands.lock().await.handle(&server_message, &channel).await;
+                channel_info_commands
+                    .lock()
+                    .await
+                    .handle(&server_message, &channel)
+                    .await;
+                shoutout.lock().await.handle(&server_message, &channel).await;
+                stream_markers.lock().await.handle(&server_message, &channel).await;
+                followage.lock().await.handle(&server_message, &channel).await;
+                quotes.handle(&server_message, &channel).await;
+                reminders.handle(&server_message, &channel).await;
+                raffles.handle(&server_message, &channel).await;
+                points.handle(&server_message, &channel).await;
+                trivia.handle(&server_message, &channel).await;
+                chat_activity.handle(&server_message, &channel).await;
             });
         }
     })
     .await
     .unwrap();
 }
+
+/// Handlers only react to the messages of a channel.
+fn channel_login(server_message: &ServerMessage) -> Option<&str> {
+    match server_message {
+        ServerMessage::Privmsg(message) => Some(&message.channel_login),
+        ServerMessage::UserNotice(message) => Some(&message.channel_login),
+        _ => None,
+    }
+}
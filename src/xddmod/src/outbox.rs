@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::Instant;
+use twitch_irc::message::PrivmsgMessage;
+use twitch_irc::message::ServerMessage;
+use twitch_irc::message::UserStateMessage;
+
+use crate::auth::IRCClient;
+
+// https://dev.twitch.tv/docs/irc/#rate-limits
+pub const MESSAGE_MAX_CHARS: usize = 500;
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(30);
+pub const RATE_LIMIT: usize = 20;
+pub const MOD_RATE_LIMIT: usize = 100;
+
+// Appended to a message identical to the previous one to bypass the Twitch duplicate message check.
+const DUPLICATE_BYPASS_SUFFIX: &str = " \u{e0000}";
+
+/// Central queue of outgoing chat messages.
+///
+/// Every message goes through a per-channel worker that splits it on word boundaries to fit Twitch's max length,
+/// waits for the channel rate limit (based on the bot mod status) and bypasses the duplicate message check.
+#[derive(Clone)]
+pub struct Outbox {
+    sender: UnboundedSender<Command>,
+}
+
+impl Outbox {
+    pub fn new(irc_client: IRCClient) -> Self {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(dispatch(irc_client, receiver));
+        Self { sender }
+    }
+
+    pub fn say(&self, channel: &str, text: String) {
+        self.enqueue(OutgoingMessage {
+            channel: channel.into(),
+            text,
+            reply_to_message_id: None,
+        })
+    }
+
+    pub fn say_in_reply_to(&self, message: &PrivmsgMessage, text: String) {
+        self.enqueue(OutgoingMessage {
+            channel: message.channel_login.clone(),
+            text,
+            reply_to_message_id: Some(message.message_id.clone()),
+        })
+    }
+
+    /// Keeps track of the bot mod status in every joined channel by looking at `USERSTATE` messages.
+    pub fn track_user_state(&self, server_message: &ServerMessage) {
+        if let ServerMessage::UserState(UserStateMessage {
+            channel_login, badges, ..
+        }) = server_message
+        {
+            let is_mod = badges.iter().any(|b| b.name == "moderator" || b.name == "broadcaster");
+            if let Err(error) = self.sender.send(Command::SetModStatus {
+                channel: channel_login.clone(),
+                is_mod,
+            }) {
+                eprintln!(
+                    "Error tracking mod status for channel {:?}, error: {:?}",
+                    channel_login, error
+                );
+            }
+        }
+    }
+
+    fn enqueue(&self, outgoing_message: OutgoingMessage) {
+        if let Err(error) = self.sender.send(Command::Send(outgoing_message)) {
+            eprintln!("Error enqueuing outgoing message, error: {:?}", error);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutgoingMessage {
+    pub channel: String,
+    pub text: String,
+    pub reply_to_message_id: Option<String>,
+}
+
+#[derive(Debug)]
+enum Command {
+    Send(OutgoingMessage),
+    SetModStatus { channel: String, is_mod: bool },
+}
+
+async fn dispatch(irc_client: IRCClient, mut receiver: UnboundedReceiver<Command>) {
+    let mut channel_senders: HashMap<String, UnboundedSender<Command>> = HashMap::new();
+
+    while let Some(command) = receiver.recv().await {
+        let channel = match &command {
+            Command::Send(OutgoingMessage { channel, .. }) | Command::SetModStatus { channel, .. } => channel.clone(),
+        };
+
+        let channel_sender = channel_senders.entry(channel).or_insert_with(|| {
+            let (channel_sender, channel_receiver) = tokio::sync::mpsc::unbounded_channel();
+            tokio::spawn(send_to_channel(irc_client.clone(), channel_receiver));
+            channel_sender
+        });
+
+        if let Err(error) = channel_sender.send(command) {
+            eprintln!("Error dispatching outgoing message, error: {:?}", error);
+        }
+    }
+}
+
+async fn send_to_channel(irc_client: IRCClient, mut receiver: UnboundedReceiver<Command>) {
+    let mut rate_limiter = RateLimiter::new(RATE_LIMIT, RATE_LIMIT_WINDOW);
+    let mut last_sent_text: Option<String> = None;
+
+    while let Some(command) = receiver.recv().await {
+        let outgoing_message = match command {
+            Command::Send(outgoing_message) => outgoing_message,
+            Command::SetModStatus { is_mod, .. } => {
+                rate_limiter.limit = if is_mod { MOD_RATE_LIMIT } else { RATE_LIMIT };
+                continue;
+            }
+        };
+
+        for (idx, chunk) in split_message(&outgoing_message.text, chunk_max_chars())
+            .into_iter()
+            .enumerate()
+        {
+            let text = bypass_duplicate(chunk, last_sent_text.as_deref());
+
+            if let Some(wait) = rate_limiter.wait_time(Instant::now()) {
+                tokio::time::sleep(wait).await;
+            }
+            rate_limiter.record(Instant::now());
+
+            // Only the first chunk is sent as a reply, the rest follows as plain messages.
+            let result = match (&outgoing_message.reply_to_message_id, idx) {
+                (Some(message_id), 0) => {
+                    irc_client
+                        .say_in_reply_to(&(outgoing_message.channel.as_str(), message_id.as_str()), text.clone())
+                        .await
+                }
+                _ => irc_client.say(outgoing_message.channel.clone(), text.clone()).await,
+            };
+
+            match result {
+                Ok(()) => last_sent_text = Some(text),
+                Err(error) => eprintln!("Error sending message {:?}, error: {:?}", outgoing_message, error),
+            }
+        }
+    }
+}
+
+/// Sliding window accounting of the messages sent in a channel.
+#[derive(Debug)]
+struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    sent_at: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            sent_at: VecDeque::new(),
+        }
+    }
+
+    pub fn wait_time(&mut self, now: Instant) -> Option<Duration> {
+        while self
+            .sent_at
+            .front()
+            .is_some_and(|sent_at| now.duration_since(*sent_at) >= self.window)
+        {
+            self.sent_at.pop_front();
+        }
+
+        if self.sent_at.len() < self.limit {
+            return None;
+        }
+
+        let oldest_in_window = self.sent_at.get(self.sent_at.len() - self.limit)?;
+        Some(self.window.saturating_sub(now.duration_since(*oldest_in_window)))
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        self.sent_at.push_back(now);
+    }
+}
+
+/// Leaves room for the [`DUPLICATE_BYPASS_SUFFIX`] appended to repeated chunks.
+fn chunk_max_chars() -> usize {
+    MESSAGE_MAX_CHARS - DUPLICATE_BYPASS_SUFFIX.chars().count()
+}
+
+fn bypass_duplicate(text: String, last_sent_text: Option<&str>) -> String {
+    match last_sent_text {
+        Some(last_sent_text) if last_sent_text == text => format!("{}{}", text, DUPLICATE_BYPASS_SUFFIX),
+        _ => text,
+    }
+}
+
+/// Splits the text in chunks of at most `max_chars` chars on word boundaries.
+/// Words longer than `max_chars` are split as well.
+pub fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    let mut current_chars = 0;
+
+    for word in text.split_whitespace() {
+        let mut word = word;
+        let mut word_chars = word.chars().count();
+
+        while word_chars > max_chars {
+            if current_chars > 0 {
+                chunks.push(std::mem::take(&mut current));
+                current_chars = 0;
+            }
+            let split_at = word.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(word.len());
+            chunks.push(word[..split_at].to_owned());
+            word = &word[split_at..];
+            word_chars -= max_chars;
+        }
+
+        if word.is_empty() {
+            continue;
+        }
+
+        if current_chars > 0 && current_chars + 1 + word_chars > max_chars {
+            chunks.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+
+        if current_chars > 0 {
+            current.push(' ');
+            current_chars += 1;
+        }
+        current.push_str(word);
+        current_chars += word_chars;
+    }
+
+    if current_chars > 0 {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_message_works_as_expected() {
+        assert!(split_message("", 10).is_empty());
+        assert!(split_message("   ", 10).is_empty());
+        assert_eq!(vec!["foo bar"], split_message("foo bar", 10));
+        assert_eq!(vec!["foo bar", "baz"], split_message("foo bar baz", 7));
+        assert_eq!(vec!["foo", "bar", "baz"], split_message("foo  bar  baz", 5));
+        assert_eq!(vec!["foo", "barba", "zqux"], split_message("foo barbazqux", 5));
+        assert_eq!(vec!["ööö", "öö"], split_message("ööööö", 3));
+    }
+
+    #[test]
+    fn test_bypass_duplicate_works_as_expected() {
+        assert_eq!("foo", bypass_duplicate("foo".into(), None));
+        assert_eq!("foo", bypass_duplicate("foo".into(), Some("bar")));
+        assert_eq!("foo \u{e0000}", bypass_duplicate("foo".into(), Some("foo")));
+    }
+
+    #[test]
+    fn test_repeated_full_length_chunks_fit_message_max_chars() {
+        let text = "a".repeat(chunk_max_chars() * 2);
+        let mut last_sent_text: Option<String> = None;
+
+        let sent: Vec<String> = split_message(&text, chunk_max_chars())
+            .into_iter()
+            .map(|chunk| {
+                let text = bypass_duplicate(chunk, last_sent_text.as_deref());
+                last_sent_text = Some(text.clone());
+                text
+            })
+            .collect();
+
+        assert_eq!(2, sent.len());
+        assert!(sent[1].ends_with(DUPLICATE_BYPASS_SUFFIX));
+        assert!(sent.iter().all(|s| s.chars().count() <= MESSAGE_MAX_CHARS));
+    }
+
+    #[test]
+    fn test_rate_limiter_works_as_expected() {
+        let mut rate_limiter = RateLimiter::new(2, Duration::from_secs(30));
+        let start = Instant::now();
+
+        assert_eq!(None, rate_limiter.wait_time(start));
+        rate_limiter.record(start);
+        assert_eq!(None, rate_limiter.wait_time(start + Duration::from_secs(10)));
+        rate_limiter.record(start + Duration::from_secs(10));
+
+        assert_eq!(
+            Some(Duration::from_secs(10)),
+            rate_limiter.wait_time(start + Duration::from_secs(20))
+        );
+        assert_eq!(None, rate_limiter.wait_time(start + Duration::from_secs(30)));
+
+        rate_limiter.limit = MOD_RATE_LIMIT;
+        rate_limiter.record(start + Duration::from_secs(30));
+        assert_eq!(None, rate_limiter.wait_time(start + Duration::from_secs(31)));
+    }
+}
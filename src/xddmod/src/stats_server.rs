@@ -0,0 +1,67 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::Path;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Json;
+use axum::Router;
+use tokio::net::TcpListener;
+
+use crate::handlers::chat_activity::core::ChatActivity;
+
+type AppState = Arc<ChatActivity<'static>>;
+
+/// Serves the chat activity stats as JSON:
+/// - `GET /stats/:channel`: messages per minute, stream chatters, top chatters and emotes.
+/// - `GET /stats/:channel/users/:login`: activity of the user, 404 if never seen in chat.
+pub async fn serve(socket_addr: SocketAddr, chat_activity: AppState) {
+    let app = Router::new()
+        .route("/stats/:channel", get(channel_stats))
+        .route("/stats/:channel/users/:login", get(user_activity))
+        .with_state(chat_activity);
+
+    let listener = match TcpListener::bind(&socket_addr).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Error binding stats server to {:?}, error: {:?}", socket_addr, error);
+            return;
+        }
+    };
+    if let Err(error) = axum::serve(listener, app.into_make_service()).await {
+        eprintln!("Error serving stats, error: {:?}", error);
+    }
+}
+
+async fn channel_stats(State(chat_activity): State<AppState>, Path(channel): Path<String>) -> Response {
+    match chat_activity.channel_stats(&channel.to_lowercase()).await {
+        Ok(channel_stats) => Json(channel_stats).into_response(),
+        Err(error) => {
+            eprintln!("Error loading stats of channel {:?}, error: {:?}", channel, error);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn user_activity(
+    State(chat_activity): State<AppState>,
+    Path((channel, login)): Path<(String, String)>,
+) -> Response {
+    match chat_activity
+        .user_activity(&channel.to_lowercase(), &login.to_lowercase())
+        .await
+    {
+        Ok(Some(user_activity)) => Json(user_activity).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            eprintln!(
+                "Error loading activity of user {:?} in channel {:?}, error: {:?}",
+                login, channel, error
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
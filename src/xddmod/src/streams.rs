@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use twitch_api::helix::streams::get_streams::GetStreamsRequest;
+use twitch_api::helix::streams::Stream;
+use twitch_api::twitch_oauth2::TwitchToken;
+use twitch_api::twitch_oauth2::UserToken;
+use twitch_api::types::UserNameRef;
+use twitch_api::HelixClient;
+
+use crate::apis::twitch;
+use crate::apis::twitch::eventsub::Event;
+
+pub const POLLING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// In memory cache of the stream status of the joined channels, kept up to date by polling Helix and by EventSub
+/// `stream.online` / `stream.offline` notifications.
+#[derive(Clone, Default)]
+pub struct Streams(Arc<RwLock<HashMap<String, StreamStatus>>>);
+
+/// Exposed as `stream` in the templates context, e.g. `{% if stream.status == "live" %}{{ stream.title }}{% endif %}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum StreamStatus {
+    Live(LiveStream),
+    Offline,
+}
+
+/// Title, category and viewers are empty when the stream went live through EventSub until the next Helix poll.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiveStream {
+    pub title: String,
+    pub category: String,
+    pub viewer_count: usize,
+    pub started_at: DateTime<Utc>,
+}
+
+impl Streams {
+    pub fn start_polling(
+        &self,
+        helix_client: HelixClient<'static, reqwest::Client>,
+        mut token: UserToken,
+        channel: String,
+        interval: Duration,
+    ) {
+        let streams = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+
+                let logins: &[&UserNameRef] = &[channel.as_str().into()];
+                let request = GetStreamsRequest::user_logins(logins);
+
+                match helix_client.req_get(request, &token).await {
+                    Ok(response) => match StreamStatus::try_from(response.data.first()) {
+                        Ok(stream_status) => streams.set(&channel, stream_status),
+                        Err(error) => eprintln!("Error parsing stream of channel {:?}, error: {:?}", channel, error),
+                    },
+                    Err(error) if twitch::helpers::is_unauthorized_error(&error) => {
+                        eprintln!("Refreshing token for stream polling");
+                        if let Err(error) = token.refresh_token(helix_client.get_client()).await {
+                            eprintln!("Error refreshing token for stream polling, error: {:?}", error);
+                        }
+                    }
+                    Err(error) => eprintln!("Error polling stream of channel {:?}, error: {:?}", channel, error),
+                }
+            }
+        });
+    }
+
+    pub fn set(&self, channel: &str, stream_status: StreamStatus) {
+        match self.0.write() {
+            Ok(mut streams) => {
+                streams.insert(channel.into(), stream_status);
+            }
+            Err(error) => eprintln!("Cannot get Streams write lock, error: {:?}", error),
+        }
+    }
+
+    pub fn handle_event(&self, event: &Event) {
+        match event {
+            Event::StreamOnline(stream_online) => {
+                let channel = event.broadcaster_user_login();
+                if !matches!(self.get(channel), Some(StreamStatus::Live(_))) {
+                    let live_stream = LiveStream {
+                        title: String::new(),
+                        category: String::new(),
+                        viewer_count: 0,
+                        started_at: stream_online.started_at,
+                    };
+                    self.set(channel, StreamStatus::Live(live_stream));
+                }
+            }
+            Event::StreamOffline(_) => self.set(event.broadcaster_user_login(), StreamStatus::Offline),
+            _ => {}
+        }
+    }
+
+    /// `None` until the stream status of the channel is known.
+    pub fn get(&self, channel: &str) -> Option<StreamStatus> {
+        match self.0.read() {
+            Ok(streams) => streams.get(channel).cloned(),
+            Err(error) => {
+                eprintln!("Cannot get Streams read lock, error: {:?}", error);
+                None
+            }
+        }
+    }
+
+    /// Category of the live stream of the channel, if known.
+    pub fn category(&self, channel: &str) -> Option<String> {
+        match self.get(channel)? {
+            StreamStatus::Live(live_stream) if !live_stream.category.is_empty() => Some(live_stream.category),
+            _ => None,
+        }
+    }
+
+    /// `None` until the stream status of the channel is known.
+    pub fn is_live(&self, channel: &str) -> Option<bool> {
+        self.get(channel).map(|s| matches!(s, StreamStatus::Live(_)))
+    }
+}
+
+impl TryFrom<Option<&Stream>> for StreamStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(stream: Option<&Stream>) -> Result<Self, Self::Error> {
+        Ok(match stream {
+            Some(stream) => Self::Live(LiveStream {
+                title: stream.title.clone(),
+                category: stream.game_name.clone(),
+                viewer_count: stream.viewer_count,
+                started_at: stream.started_at.as_str().parse()?,
+            }),
+            None => Self::Offline,
+        })
+    }
+}
@@ -1,38 +1,211 @@
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use chrono_tz::Tz;
+use minijinja::context;
 use minijinja::Environment;
 use minijinja::ErrorKind;
+use minijinja::State;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 use sqlx::types::chrono::DateTime;
 use sqlx::types::chrono::FixedOffset;
 use sqlx::types::chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::handlers::counters::persistence::Counter;
+use crate::handlers::persistence::Channel;
+use crate::handlers::vars::persistence::Var;
+use crate::streams::Streams;
 
 pub fn build_global_templates_env<'a>() -> Environment<'a> {
     let mut template_env = Environment::new();
-    template_env.add_filter("now", now);
-    template_env.add_filter("format_date_time", format_date_time);
+    template_env.add_filter("now", now_in_channel_timezone);
+    template_env.add_function("now", now_in_channel_timezone);
+    template_env.add_filter("format_date_time", format_date_time_in_channel_timezone);
     template_env.add_filter("sub_date_times", sub_date_times);
     template_env.add_filter("format_duration", format_duration);
+    template_env.add_filter("parse_duration", |duration: &str| {
+        parse_duration(duration).map(minijinja::value::Value::from_serialize)
+    });
     template_env.add_filter("wrap_string", wrap_string);
     template_env.add_filter("add_duration", add_duration);
+    template_env.add_function("counter", |state: &State, name: String| {
+        with_channel_state(state, |channel_state| channel_state.counter(&name))
+    });
+    template_env.add_function("incr_counter", |state: &State, name: String, by: Option<i64>| {
+        with_channel_state(state, |channel_state| {
+            channel_state.update_counter(&name, CounterChange::Incr(by.unwrap_or(1)))
+        })
+    });
+    template_env.add_function("reset_counter", |state: &State, name: String| {
+        with_channel_state(state, |channel_state| {
+            channel_state.update_counter(&name, CounterChange::Set(0))
+        })
+    });
+    template_env.add_function("var", |state: &State, key: String| {
+        with_channel_state(state, |channel_state| channel_state.vars.get(&key).cloned())
+    });
 
     template_env
 }
 
+/// Renders the template injecting the channel profile, its stream status and its counters and vars in its context.
+/// Counters updated by the template are persisted once rendered.
+pub async fn render_template<S: Serialize>(
+    template_env: &Environment<'_>,
+    template: &str,
+    channel: &Channel,
+    streams: &Streams,
+    db_pool: &SqlitePool,
+    ctx: Option<&S>,
+) -> Result<String, minijinja::Error> {
+    let stream = streams.get(&channel.name);
+    let channel_state = minijinja::value::Value::from_object(ChannelState::fetch(&channel.name, db_pool).await?);
+    let ctx = match ctx {
+        Some(ctx) => context! {
+            channel => channel,
+            stream => stream,
+            channel_state => channel_state.clone(),
+            ..minijinja::value::Value::from_serialize(ctx)
+        },
+        None => context! { channel => channel, stream => stream, channel_state => channel_state.clone() },
+    };
+    let rendered = template_env.render_str(template, ctx).map(|s| s.trim().into())?;
+
+    if let Some(channel_state) = channel_state.downcast_object_ref::<ChannelState>() {
+        channel_state.persist(&channel.name, db_pool).await?;
+    }
+
+    Ok(rendered)
+}
+
+/// Channel counters and vars fetched before rendering so that templates don't need to query the DB.
+#[derive(Debug)]
+struct ChannelState {
+    /// Counters values along with their changes made while rendering.
+    counters: Mutex<HashMap<String, (i64, Option<CounterChange>)>>,
+    vars: HashMap<String, String>,
+}
+
+impl minijinja::value::Object for ChannelState {}
+
+impl ChannelState {
+    async fn fetch(channel: &str, db_pool: &SqlitePool) -> Result<Self, minijinja::Error> {
+        let counters = Counter::all_by_channel(channel, db_pool)
+            .await
+            .map_err(|e| db_error("counters", e))?;
+        let vars = Var::all_by_channel(channel, db_pool)
+            .await
+            .map_err(|e| db_error("vars", e))?;
+
+        Ok(Self {
+            counters: Mutex::new(counters.into_iter().map(|c| (c.name, (c.value, None))).collect()),
+            vars: vars.into_iter().map(|v| (v.key, v.value)).collect(),
+        })
+    }
+
+    fn counter(&self, name: &str) -> i64 {
+        self.counters
+            .lock()
+            .ok()
+            .and_then(|counters| counters.get(name).map(|(value, _)| *value))
+            .unwrap_or_default()
+    }
+
+    fn update_counter(&self, name: &str, change: CounterChange) -> i64 {
+        let Ok(mut counters) = self.counters.lock() else {
+            return 0;
+        };
+
+        let (value, counter_change) = counters.entry(name.into()).or_default();
+        *value = change.apply(*value);
+        *counter_change = Some(counter_change.map_or(change, |counter_change| counter_change.then(change)));
+
+        *value
+    }
+
+    async fn persist(&self, channel: &str, db_pool: &SqlitePool) -> Result<(), minijinja::Error> {
+        let counters_changes: Vec<(String, CounterChange)> = self
+            .counters
+            .lock()
+            .map(|mut counters| {
+                counters
+                    .iter_mut()
+                    .filter_map(|(name, (_, counter_change))| Some((name.clone(), counter_change.take()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (name, change) in counters_changes {
+            match change {
+                CounterChange::Incr(by) => Counter::incr(channel, &name, by, db_pool).await,
+                CounterChange::Set(value) => Counter::set(channel, &name, value, db_pool).await,
+            }
+            .map_err(|e| db_error(&name, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Increments are persisted as such to not override concurrent ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CounterChange {
+    Incr(i64),
+    Set(i64),
+}
+
+impl CounterChange {
+    fn apply(self, value: i64) -> i64 {
+        match self {
+            CounterChange::Incr(by) => value + by,
+            CounterChange::Set(value) => value,
+        }
+    }
+
+    fn then(self, next: CounterChange) -> CounterChange {
+        match (self, next) {
+            (CounterChange::Incr(by), CounterChange::Incr(next_by)) => CounterChange::Incr(by + next_by),
+            (CounterChange::Set(value), CounterChange::Incr(by)) => CounterChange::Set(value + by),
+            (_, CounterChange::Set(value)) => CounterChange::Set(value),
+        }
+    }
+}
+
 fn now(timezone: Option<&str>) -> Result<String, minijinja::Error> {
     let timezone = timezone.map(parse_timezone).unwrap_or_else(|| Ok(Tz::UTC))?;
     Ok(Utc::now().with_timezone(&timezone).to_rfc3339())
 }
 
+fn now_in_channel_timezone(state: &State, timezone: Option<String>) -> Result<String, minijinja::Error> {
+    now(timezone.or_else(|| channel_timezone(state)).as_deref())
+}
+
 fn format_date_time(date_time: &str, format: &str) -> Result<String, minijinja::Error> {
     let date_time = parse_date_time_from_rfc3339(date_time)?;
     Ok(date_time.format(format).to_string())
 }
 
+fn format_date_time_in_channel_timezone(
+    state: &State,
+    date_time: String,
+    format: String,
+    timezone: Option<String>,
+) -> Result<String, minijinja::Error> {
+    match timezone.or_else(|| channel_timezone(state)) {
+        Some(timezone) => {
+            let date_time = parse_date_time_from_rfc3339(&date_time)?;
+            let timezone = parse_timezone(&timezone)?;
+            Ok(date_time.with_timezone(&timezone).format(&format).to_string())
+        }
+        None => format_date_time(&date_time, &format),
+    }
+}
+
 fn sub_date_times(from_date_time: &str, to_date_time: &str) -> Result<minijinja::value::Value, minijinja::Error> {
     let from_date_time = parse_date_time_from_rfc3339(from_date_time)?;
     let to_date_time = parse_date_time_from_rfc3339(to_date_time)?;
@@ -54,13 +227,63 @@ fn sub_date_times(from_date_time: &str, to_date_time: &str) -> Result<minijinja:
 
 fn format_duration(duration: &minijinja::value::Value) -> Result<String, minijinja::Error> {
     let duration: std::time::Duration = from_json_value(to_json_value(duration)?)?;
+    Ok(humanize_duration(duration))
+}
 
+/// E.g. "1 hour 30 minutes".
+pub fn humanize_duration(duration: std::time::Duration) -> String {
     let mut formatter = timeago::Formatter::new();
     formatter.ago("");
     formatter.too_low("0");
     formatter.num_items(3);
 
-    Ok(formatter.convert(duration))
+    formatter.convert(duration)
+}
+
+/// Parses durations like "1d", "1h30m", "90s" or "2h 15m". Plain numbers are minutes.
+pub fn parse_duration(duration: &str) -> Result<std::time::Duration, minijinja::Error> {
+    let error = || {
+        minijinja::Error::new(
+            ErrorKind::InvalidOperation,
+            format!("Cannot parse {:?} as duration.", duration),
+        )
+    };
+
+    let trimmed = duration.trim();
+    if let Ok(minutes) = trimmed.parse::<u64>() {
+        return minutes
+            .checked_mul(60)
+            .map(std::time::Duration::from_secs)
+            .ok_or_else(error);
+    }
+
+    let mut secs: u64 = 0;
+    let mut amount = String::new();
+    for c in trimmed.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            amount.push(c);
+            continue;
+        }
+        let unit_secs = match c.to_ascii_lowercase() {
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(error()),
+        };
+        secs = amount
+            .parse::<u64>()
+            .ok()
+            .and_then(|amount| amount.checked_mul(unit_secs))
+            .and_then(|amount_secs| secs.checked_add(amount_secs))
+            .ok_or_else(error)?;
+        amount.clear();
+    }
+    if !amount.is_empty() || trimmed.is_empty() {
+        return Err(error());
+    }
+
+    Ok(std::time::Duration::from_secs(secs))
 }
 
 fn wrap_string(string: &str, wrapping: &str) -> Result<String, minijinja::Error> {
@@ -83,6 +306,40 @@ fn add_duration(date_time: &str, duration: &minijinja::value::Value) -> Result<S
     Ok((date_time + duration).to_rfc3339())
 }
 
+fn with_channel_state<T>(state: &State, f: impl FnOnce(&ChannelState) -> T) -> Result<T, minijinja::Error> {
+    let channel_state = state.lookup("channel_state").ok_or_else(|| {
+        minijinja::Error::new(
+            ErrorKind::InvalidOperation,
+            "Missing channel state in template context.",
+        )
+    })?;
+    channel_state
+        .downcast_object_ref::<ChannelState>()
+        .map(f)
+        .ok_or_else(|| {
+            minijinja::Error::new(
+                ErrorKind::InvalidOperation,
+                "Invalid channel state in template context.",
+            )
+        })
+}
+
+fn channel_timezone(state: &State) -> Option<String> {
+    channel_attr(state, "timezone")
+}
+
+fn channel_attr(state: &State, attr: &str) -> Option<String> {
+    state.lookup("channel")?.get_attr(attr).ok()?.as_str().map(String::from)
+}
+
+fn db_error(name: &str, error: sqlx::Error) -> minijinja::Error {
+    minijinja::Error::new(
+        ErrorKind::InvalidOperation,
+        format!("Cannot query {:?} from DB, error {:?}.", name, error),
+    )
+    .with_source(error)
+}
+
 fn parse_timezone(timezone: &str) -> Result<Tz, minijinja::Error> {
     Tz::from_str(timezone).map_err(|e| {
         minijinja::Error::new(
@@ -141,6 +398,9 @@ mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::handlers::persistence::test_db_pool;
+    use crate::streams::LiveStream;
+    use crate::streams::StreamStatus;
 
     #[test]
     fn test_now_works_as_expected() {
@@ -212,6 +472,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_duration_works_as_expected() {
+        assert_eq!(std::time::Duration::from_secs(10 * 60), parse_duration("10").unwrap());
+        assert_eq!(std::time::Duration::from_secs(90), parse_duration("90s").unwrap());
+        assert_eq!(
+            std::time::Duration::from_secs(90 * 60),
+            parse_duration("1h30m").unwrap()
+        );
+        assert_eq!(
+            std::time::Duration::from_secs(24 * 60 * 60 + 15 * 60),
+            parse_duration("1d 15M").unwrap()
+        );
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("1w").is_err());
+        assert!(parse_duration("307445734561825861").is_err());
+        assert!(parse_duration("213503982334601d").is_err());
+        assert!(parse_duration("18446744073709551615s 1s").is_err());
+    }
+
+    #[test]
+    fn test_date_time_filters_default_to_channel_timezone() {
+        let template = r#"{{ date_time|format_date_time("%H:%M") }} {{ date_time|format_date_time("%H:%M", "UTC") }}"#;
+        let env = build_global_templates_env();
+
+        assert_eq!(
+            "07:52 07:52",
+            env.render_str(template, context! { date_time => "2023-04-16T07:52:13Z" })
+                .unwrap()
+        );
+        assert_eq!(
+            "09:52 07:52",
+            env.render_str(
+                template,
+                context! {
+                    date_time => "2023-04-16T07:52:13Z",
+                    channel => context! { name => "foo", timezone => "Europe/Berlin" }
+                }
+            )
+            .unwrap()
+        );
+
+        let now = env
+            .render_str(
+                "{{ now() }}",
+                context! { channel => context! { name => "foo", timezone => "Asia/Tokyo" } },
+            )
+            .unwrap();
+        assert_eq!(
+            "+09:00",
+            DateTime::parse_from_rfc3339(&now).unwrap().timezone().to_string()
+        );
+    }
+
     #[test]
     fn test_wrap_string_as_expected() {
         let template = r#"
@@ -242,4 +557,161 @@ mod tests {
             env.render_str(template, template_context).unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn test_counters_functions_work_as_expected() {
+        let template = r#"{{ counter("deaths") }} {{ incr_counter("deaths") }} {{ incr_counter("deaths", 41) }} {{ reset_counter("deaths") }} {{ incr_counter("deaths", 2) }}"#;
+        let env = build_global_templates_env();
+        let db_pool = test_db_pool().await;
+        let channel = test_channel("foo");
+
+        assert_eq!(
+            "0 1 42 0 2",
+            render_template::<()>(&env, template, &channel, &Streams::default(), &db_pool, None)
+                .await
+                .unwrap()
+        );
+        assert_eq!(2, Counter::get("foo", "deaths", &db_pool).await.unwrap().unwrap().value);
+
+        Counter::incr("foo", "deaths", 40, &db_pool).await.unwrap();
+        assert_eq!(
+            "42 43",
+            render_template::<()>(
+                &env,
+                r#"{{ counter("deaths") }} {{ incr_counter("deaths") }}"#,
+                &channel,
+                &Streams::default(),
+                &db_pool,
+                None
+            )
+            .await
+            .unwrap()
+        );
+        assert_eq!(
+            43,
+            Counter::get("foo", "deaths", &db_pool).await.unwrap().unwrap().value
+        );
+        assert!(env.render_str(template, context! {}).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_var_function_works_as_expected() {
+        let template = r#"{% if var("goal") %}Goal: {{ var("goal") }}{% else %}No goal{% endif %}"#;
+        let env = build_global_templates_env();
+        let db_pool = test_db_pool().await;
+
+        assert_eq!(
+            "No goal",
+            render_template::<()>(
+                &env,
+                template,
+                &test_channel("foo"),
+                &Streams::default(),
+                &db_pool,
+                None
+            )
+            .await
+            .unwrap()
+        );
+
+        Var::set("foo", "goal", "Challenger", "bar", &db_pool).await.unwrap();
+        assert_eq!(
+            "Goal: Challenger",
+            render_template::<()>(
+                &env,
+                template,
+                &test_channel("foo"),
+                &Streams::default(),
+                &db_pool,
+                None
+            )
+            .await
+            .unwrap()
+        );
+        assert_eq!(
+            "No goal",
+            render_template::<()>(
+                &env,
+                template,
+                &test_channel("baz"),
+                &Streams::default(),
+                &db_pool,
+                None
+            )
+            .await
+            .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_date_time_filters_keep_input_timezone_without_channel_profile() {
+        let template = r#"{{ date_time|format_date_time("%H:%M") }}"#;
+        let env = build_global_templates_env();
+        let db_pool = test_db_pool().await;
+        let channel = Channel::by_name_or_default("foo", &db_pool).await;
+
+        assert_eq!(
+            "09:52",
+            render_template(
+                &env,
+                template,
+                &channel,
+                &Streams::default(),
+                &db_pool,
+                Some(context! { date_time => "2023-04-16T09:52:13+02:00" })
+            )
+            .await
+            .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_render_template_injects_stream_of_channel() {
+        let template = r#"{% if stream.status == "live" %}{{ stream.title }} ({{ stream.category }}){% elif stream %}Offline{% else %}Unknown{% endif %}"#;
+        let streams = Streams::default();
+        let env = build_global_templates_env();
+        let db_pool = test_db_pool().await;
+        let channel = test_channel("foo");
+
+        assert_eq!(
+            "Unknown",
+            render_template::<()>(&env, template, &channel, &streams, &db_pool, None)
+                .await
+                .unwrap()
+        );
+
+        streams.set("foo", StreamStatus::Offline);
+        assert_eq!(
+            "Offline",
+            render_template::<()>(&env, template, &channel, &streams, &db_pool, None)
+                .await
+                .unwrap()
+        );
+
+        streams.set(
+            "foo",
+            StreamStatus::Live(LiveStream {
+                title: "Road to Challenger".into(),
+                category: "League of Legends".into(),
+                viewer_count: 42,
+                started_at: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+            }),
+        );
+        assert_eq!(
+            "Road to Challenger (League of Legends)",
+            render_template::<()>(&env, template, &channel, &streams, &db_pool, None)
+                .await
+                .unwrap()
+        );
+    }
+
+    fn test_channel(name: &str) -> Channel {
+        Channel {
+            name: name.into(),
+            caster: "Foo".into(),
+            date_of_birth: None,
+            timezone: Some("UTC".into()),
+            seven_tv_id: None,
+        }
+    }
 }